@@ -11,9 +11,24 @@ lazy_static! {
 }
 
 pub fn find_version(package_name: &'static str, cargo_lock: &str) -> Option<Version> {
+    find_version_by(cargo_lock, |capture_package_name| package_name == capture_package_name)
+}
+
+/// Same as `find_version`, but treats `-` and `_` in the package names as
+/// equivalent (ex. `wasm_rgame` finds `wasm-rgame`).
+pub fn find_version_normalized(package_name: &str, cargo_lock: &str) -> Option<Version> {
+    let package_name = normalize(package_name);
+    find_version_by(cargo_lock, |capture_package_name| package_name == normalize(capture_package_name))
+}
+
+fn normalize(package_name: &str) -> String {
+    package_name.replace("_", "-")
+}
+
+fn find_version_by(cargo_lock: &str, matches_package_name: impl Fn(&str) -> bool) -> Option<Version> {
     for captures in VERSION_MATCH.captures_iter(cargo_lock) {
         let capture_package_name = captures.get(1).unwrap();
-        if package_name != capture_package_name.as_str() {
+        if !matches_package_name(capture_package_name.as_str()) {
             continue;
         }
 
@@ -40,5 +55,26 @@ mod tests {
         assert_eq!(find_version("ansi_term", example_lock), Some(Version::parse("0.11.0").unwrap()));
         assert_eq!(find_version("arrayvec", example_lock), Some(Version::parse("0.4.7").unwrap()));
         assert_eq!(find_version("atty", example_lock), Some(Version::parse("0.2.10").unwrap()));
+        assert_eq!(find_version("wasm-rgame", example_lock), Some(Version::parse("0.3.1").unwrap()));
+    }
+
+    #[test]
+    fn find_version_is_exact() {
+        let example_lock = include_str!("../data/Cargo.lock.example");
+
+        assert_eq!(find_version("wasm_rgame", example_lock), None);
+        assert_eq!(find_version("ansi-term", example_lock), None);
+    }
+
+    #[test]
+    fn find_version_normalized_ignores_hyphen_underscore() {
+        let example_lock = include_str!("../data/Cargo.lock.example");
+
+        assert_eq!(find_version_normalized("wasm-rgame", example_lock), Some(Version::parse("0.3.1").unwrap()));
+        assert_eq!(find_version_normalized("wasm_rgame", example_lock), Some(Version::parse("0.3.1").unwrap()));
+        assert_eq!(find_version_normalized("ansi_term", example_lock), Some(Version::parse("0.11.0").unwrap()));
+        assert_eq!(find_version_normalized("ansi-term", example_lock), Some(Version::parse("0.11.0").unwrap()));
+        assert_eq!(find_version_normalized("aho_corasick", example_lock), Some(Version::parse("0.6.4").unwrap()));
+        assert_eq!(find_version_normalized("wasm-rgame-js", example_lock), None);
     }
 }
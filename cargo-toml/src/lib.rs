@@ -1,9 +1,21 @@
 #[macro_use] extern crate serde_derive;
+extern crate semver;
 extern crate toml;
 
+use std::collections::BTreeMap;
+
+use semver::VersionReq;
+
 #[derive(Deserialize)]
 pub struct CargoToml {
     pub package: Package,
+    pub lib: Option<Lib>,
+    #[serde(default)]
+    pub bin: Vec<Bin>,
+    #[serde(default)]
+    pub example: Vec<Example>,
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
 }
 
 #[derive(Deserialize)]
@@ -11,10 +23,166 @@ pub struct Package {
     pub name: String,
 }
 
+#[derive(Deserialize)]
+pub struct Lib {
+    pub name: Option<String>,
+    #[serde(rename = "crate-type")]
+    pub crate_type: Option<Vec<String>>,
+}
+
+/// Normalizes the package name into the crate name the same way cargo does
+/// when naming the compiled artifact (hyphens become underscores). Cargo keeps
+/// the case, a package `MyGame` is compiled to `MyGame.wasm`.
+pub fn crate_name(package_name: &str) -> String {
+    package_name.replace("-", "_")
+}
+
+#[derive(Deserialize)]
+pub struct Bin {
+    pub name: String,
+}
+
+/// An `[[example]]` section, only needed for examples that aren't built as a
+/// binary (ex. `crate-type = ["cdylib"]`).
+#[derive(Deserialize)]
+pub struct Example {
+    pub name: String,
+    #[serde(rename = "crate-type")]
+    pub crate_type: Option<Vec<String>>,
+}
+
+/// The `[workspace]` section of a workspace root manifest.
+#[derive(Deserialize)]
+pub struct Workspace {
+    #[serde(default)]
+    pub members: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct WorkspaceManifest {
+    workspace: Option<Workspace>,
+}
+
+impl Workspace {
+    /// The `[workspace]` of the manifest, or None if the manifest isn't a
+    /// workspace root (or can't be parsed).
+    pub fn from_manifest(input: &str) -> Option<Workspace> {
+        toml::from_str::<WorkspaceManifest>(input).ok()
+            .and_then(|manifest| manifest.workspace)
+    }
+
+    /// Returns true if the package at the path (relative to the workspace root,
+    /// separated by `/`) is a member, `members` can use `*` wildcards like
+    /// `crates/*` and `exclude` excludes the directory and everything under it.
+    pub fn includes(&self, member_path: &str) -> bool {
+        let excluded = self.exclude.iter()
+            .map(|exclude| exclude.trim_end_matches('/'))
+            .any(|exclude| member_path == exclude || member_path.starts_with(&format!("{}/", exclude)));
+
+        !excluded && self.members.iter().any(|member| path_matches(member.trim_end_matches('/'), member_path))
+    }
+}
+
+/// Matches the path against the pattern component by component, a `*` in a
+/// component matches any characters within that component.
+fn path_matches(pattern: &str, path: &str) -> bool {
+    let pattern_components = pattern.split('/').collect::<Vec<_>>();
+    let path_components = path.split('/').collect::<Vec<_>>();
+
+    pattern_components.len() == path_components.len() &&
+        pattern_components.iter().zip(path_components.iter()).all(|(pattern, component)| {
+            match pattern.find('*') {
+                Some(index) => {
+                    let (prefix, suffix) = (&pattern[..index], &pattern[index + 1..]);
+                    component.len() >= prefix.len() + suffix.len() &&
+                        component.starts_with(prefix) && component.ends_with(suffix)
+                },
+                None => pattern == component,
+            }
+        })
+}
+
+/// A dependency is either the shorthand version string (`wasm-rgame = "0.3"`)
+/// or a table (`wasm-rgame = { version = "0.3" }`, `{ git = "..." }`).
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Dependency {
+    Version(String),
+    Detailed(DependencyDetail),
+}
+
+#[derive(Deserialize)]
+pub struct DependencyDetail {
+    pub version: Option<String>,
+    pub git: Option<String>,
+    pub path: Option<String>,
+}
+
+impl Dependency {
+    /// The version requirement string, git / path dependencies may not have one.
+    pub fn version(&self) -> Option<&str> {
+        match *self {
+            Dependency::Version(ref version) => Some(version),
+            Dependency::Detailed(ref detail) => detail.version.as_ref().map(|v| v.as_str()),
+        }
+    }
+}
+
 impl CargoToml {
     pub fn from_str(input: &str) -> Result<CargoToml, toml::de::Error> {
         toml::from_str(input)
     }
+
+    /// Returns true if the manifest is a virtual manifest (a `[workspace]`
+    /// without a `[package]`), which can't be parsed as a CargoToml.
+    pub fn is_virtual_manifest(input: &str) -> bool {
+        match input.parse::<toml::Value>() {
+            Ok(value) => value.get("workspace").is_some() && value.get("package").is_none(),
+            Err(_) => false,
+        }
+    }
+
+    /// The name of the compiled library, `[lib] name` overrides the
+    /// normalized package name.
+    pub fn lib_name(&self) -> String {
+        self.lib.as_ref()
+            .and_then(|lib| lib.name.clone())
+            .unwrap_or_else(|| crate_name(&self.package.name))
+    }
+
+    /// The names of the `[[bin]]` targets declared in the manifest.
+    pub fn bin_names(&self) -> Vec<&str> {
+        self.bin.iter().map(|bin| bin.name.as_str()).collect()
+    }
+
+    /// Returns true if the `[lib]` section lists the crate type (ex. "cdylib").
+    pub fn has_lib_crate_type(&self, crate_type: &str) -> bool {
+        self.lib.as_ref()
+            .and_then(|lib| lib.crate_type.as_ref())
+            .map(|crate_types| crate_types.iter().any(|c| c == crate_type))
+            .unwrap_or(false)
+    }
+
+    /// Returns true if the example is built as a library (ex. `crate-type = ["cdylib"]`)
+    /// instead of a binary, cargo names library artifacts after the normalized
+    /// crate name but keeps the hyphens of binaries.
+    pub fn is_lib_example(&self, name: &str) -> bool {
+        self.example.iter()
+            .filter(|example| example.name == name)
+            .filter_map(|example| example.crate_type.as_ref())
+            .any(|crate_types| crate_types.iter().any(|c| c != "bin"))
+    }
+
+    /// The version requirement of the dependency in `[dependencies]`, or None if
+    /// there is no such dependency, it has no version (ex. a git / path dependency)
+    /// or the requirement is invalid.
+    pub fn dependency_req(&self, name: &str) -> Option<VersionReq> {
+        self.dependencies.get(name)
+            .and_then(|dependency| dependency.version())
+            .and_then(|version| VersionReq::parse(version).ok())
+    }
 }
 
 
@@ -33,4 +201,211 @@ mod tests {
         let cargo_toml = CargoToml::from_str(&cargo_file_contents).unwrap();
         assert_eq!(cargo_toml.package.name, "cargo-toml");
     }
+
+    #[test]
+    fn detects_virtual_manifest() {
+        let virtual_manifest = include_str!("../data/Cargo.toml.virtual-manifest.example");
+
+        assert!(CargoToml::from_str(virtual_manifest).is_err());
+        assert!(CargoToml::is_virtual_manifest(virtual_manifest));
+    }
+
+    #[test]
+    fn package_manifest_is_not_virtual() {
+        assert!(!CargoToml::is_virtual_manifest(r#"
+            [package]
+            name = "my-game"
+
+            [workspace]
+        "#));
+        assert!(!CargoToml::is_virtual_manifest("[dependencies]\n"));
+    }
+
+    #[test]
+    fn parses_lib_crate_type() {
+        let cargo_toml = CargoToml::from_str(r#"
+            [package]
+            name = "my-game"
+
+            [lib]
+            crate-type = ["cdylib", "rlib"]
+        "#).unwrap();
+
+        assert_eq!(cargo_toml.lib.as_ref().unwrap().crate_type, Some(vec!["cdylib".to_owned(), "rlib".to_owned()]));
+        assert!(cargo_toml.has_lib_crate_type("cdylib"));
+        assert!(cargo_toml.has_lib_crate_type("rlib"));
+        assert!(!cargo_toml.has_lib_crate_type("staticlib"));
+    }
+
+    #[test]
+    fn parses_missing_lib_section() {
+        let cargo_toml = CargoToml::from_str(r#"
+            [package]
+            name = "my-game"
+        "#).unwrap();
+
+        assert!(cargo_toml.lib.is_none());
+        assert!(!cargo_toml.has_lib_crate_type("cdylib"));
+    }
+
+    #[test]
+    fn parses_lib_section_without_crate_type() {
+        let cargo_toml = CargoToml::from_str(r#"
+            [package]
+            name = "my-game"
+
+            [lib]
+            path = "src/lib.rs"
+        "#).unwrap();
+
+        assert!(cargo_toml.lib.unwrap().crate_type.is_none());
+    }
+
+    #[test]
+    fn parses_multiple_targets() {
+        let cargo_toml = CargoToml::from_str(r#"
+            [package]
+            name = "my-game"
+
+            [lib]
+            crate-type = ["cdylib"]
+
+            [[bin]]
+            name = "level-editor"
+            path = "src/bin/level_editor.rs"
+
+            [[bin]]
+            name = "server"
+        "#).unwrap();
+
+        assert_eq!(cargo_toml.lib_name(), "my_game");
+        assert_eq!(cargo_toml.bin_names(), vec!["level-editor", "server"]);
+    }
+
+    #[test]
+    fn parses_manifest_without_bins() {
+        let cargo_toml = CargoToml::from_str(r#"
+            [package]
+            name = "my-game"
+        "#).unwrap();
+
+        assert!(cargo_toml.bin_names().is_empty());
+    }
+
+    #[test]
+    fn parses_example_crate_type() {
+        let cargo_toml = CargoToml::from_str(r#"
+            [package]
+            name = "my-game"
+
+            [[example]]
+            name = "bouncing-balls"
+            crate-type = ["cdylib"]
+
+            [[example]]
+            name = "level-select"
+        "#).unwrap();
+
+        assert!(cargo_toml.is_lib_example("bouncing-balls"));
+        assert!(!cargo_toml.is_lib_example("level-select"));
+        assert!(!cargo_toml.is_lib_example("missing"));
+    }
+
+    #[test]
+    fn workspace_includes_members() {
+        let workspace = Workspace::from_manifest(r#"
+            [workspace]
+            members = ["game", "crates/*", "tools/"]
+            exclude = ["crates/old-game"]
+        "#).unwrap();
+
+        assert!(workspace.includes("game"));
+        assert!(workspace.includes("crates/my-game"));
+        assert!(workspace.includes("tools"));
+        assert!(!workspace.includes("crates/old-game"));
+        assert!(!workspace.includes("crates/my-game/nested"));
+        assert!(!workspace.includes("other-game"));
+    }
+
+    #[test]
+    fn workspace_from_manifest_without_workspace() {
+        assert!(Workspace::from_manifest("[package]\nname = \"my-game\"\n").is_none());
+        assert!(Workspace::from_manifest("[workspace]\n").unwrap().members.is_empty());
+    }
+
+    #[test]
+    fn crate_name_replaces_all_hyphens() {
+        assert_eq!(crate_name("my-game"), "my_game");
+        assert_eq!(crate_name("my-cool-wasm-game"), "my_cool_wasm_game");
+        assert_eq!(crate_name("my_game"), "my_game");
+    }
+
+    #[test]
+    fn crate_name_preserves_case() {
+        assert_eq!(crate_name("My-Game"), "My_Game");
+    }
+
+    #[test]
+    fn lib_name_prefers_lib_section_name() {
+        let cargo_toml = CargoToml::from_str(r#"
+            [package]
+            name = "my-game"
+        "#).unwrap();
+        assert_eq!(cargo_toml.lib_name(), "my_game");
+
+        let cargo_toml = CargoToml::from_str(r#"
+            [package]
+            name = "my-game"
+
+            [lib]
+            name = "game"
+        "#).unwrap();
+        assert_eq!(cargo_toml.lib_name(), "game");
+    }
+
+    #[test]
+    fn dependency_req_from_string_form() {
+        let cargo_toml = CargoToml::from_str(r#"
+            [package]
+            name = "my-game"
+
+            [dependencies]
+            wasm-rgame = "0.3"
+        "#).unwrap();
+
+        assert_eq!(cargo_toml.dependency_req("wasm-rgame"), Some(VersionReq::parse("0.3").unwrap()));
+        assert_eq!(cargo_toml.dependency_req("wasm-bindgen"), None);
+    }
+
+    #[test]
+    fn dependency_req_from_table_form() {
+        let cargo_toml = CargoToml::from_str(r#"
+            [package]
+            name = "my-game"
+
+            [dependencies]
+            wasm-rgame = { version = "^0.3", default-features = false }
+
+            [dependencies.wasm-bindgen]
+            version = "0.2.15"
+        "#).unwrap();
+
+        assert_eq!(cargo_toml.dependency_req("wasm-rgame"), Some(VersionReq::parse("^0.3").unwrap()));
+        assert_eq!(cargo_toml.dependency_req("wasm-bindgen"), Some(VersionReq::parse("0.2.15").unwrap()));
+    }
+
+    #[test]
+    fn dependency_req_for_git_and_path_dependencies() {
+        let cargo_toml = CargoToml::from_str(r#"
+            [package]
+            name = "my-game"
+
+            [dependencies]
+            wasm-rgame = { git = "https://github.com/DarrenTsung/wasm-rgame" }
+            wasm-rgame-js = { path = "../wasm-rgame-js" }
+        "#).unwrap();
+
+        assert_eq!(cargo_toml.dependency_req("wasm-rgame"), None);
+        assert_eq!(cargo_toml.dependency_req("wasm-rgame-js"), None);
+    }
 }
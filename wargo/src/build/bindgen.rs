@@ -0,0 +1,165 @@
+use super::*;
+
+use std::str::FromStr;
+
+/// The kind of JS output wasm-bindgen should generate.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BindgenTarget {
+    /// A global (named after the project) that doesn't require ES modules, what
+    /// the wasm-rgame-js templates are written for.
+    NoModules,
+    /// ES modules loaded directly by the browser.
+    Web,
+    /// ES modules meant to be consumed by a bundler (ex. webpack).
+    Bundler,
+}
+
+impl FromStr for BindgenTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<BindgenTarget, String> {
+        match s {
+            "no-modules" => Ok(BindgenTarget::NoModules),
+            "web" => Ok(BindgenTarget::Web),
+            "bundler" => Ok(BindgenTarget::Bundler),
+            _ => Err(format!("Invalid bindgen target: `{}`, expected one of: no-modules, web, bundler", s)),
+        }
+    }
+}
+
+/// The arguments to run wasm-bindgen with for the built wasm file.
+pub fn bindgen_args(
+    wasm_output_path: &str,
+    js_global_name: &str,
+    target_dir: &str,
+    bindgen_target: BindgenTarget,
+    typescript: bool,
+) -> Vec<String> {
+    let mut args = vec![wasm_output_path.to_owned()];
+
+    match bindgen_target {
+        BindgenTarget::NoModules => {
+            args.push("--no-modules".to_owned());
+            args.push("--no-modules-global".to_owned());
+            args.push(js_global_name.to_owned());
+        },
+        BindgenTarget::Web => {
+            args.push("--target".to_owned());
+            args.push("web".to_owned());
+        },
+        BindgenTarget::Bundler => {
+            args.push("--target".to_owned());
+            args.push("bundler".to_owned());
+        },
+    }
+
+    if !typescript {
+        args.push("--no-typescript".to_owned());
+    }
+
+    args.push("--out-dir".to_owned());
+    args.push(target_dir.to_owned());
+    args
+}
+
+/// The snippet substituted for `$BINDGEN_IMPORT` in the js files, which loads the
+/// wasm-bindgen output so a single template works with every bindgen target.
+///
+/// For `no-modules` and `web` it's the HTML `<script>` exposing the output as the
+/// `$PROJECT_NAME` global (the `web` init function gets the exports assigned to it,
+/// matching the `no-modules` global). For `bundler` it's the ES import statement
+/// for the entry script that is bundled. The output files are named after the built
+/// name, which (unlike the global) can have hyphens.
+pub fn import_snippet(built_project_name: &str, js_global_name: &str, bindgen_target: BindgenTarget) -> String {
+    match bindgen_target {
+        BindgenTarget::NoModules => format!("<script src=\"./{}.js\"></script>", built_project_name),
+        BindgenTarget::Web => format!(
+            "<script type=\"module\">import init, * as exports from \"./{}.js\"; window.{} = Object.assign(init, exports);</script>",
+            built_project_name,
+            js_global_name,
+        ),
+        BindgenTarget::Bundler => format!("import * as {} from \"./{}\";", js_global_name, built_project_name),
+    }
+}
+
+/// The command line of running the command with the arguments, quoted so it can
+/// be copy-pasted into a (POSIX) shell.
+pub fn command_line(command: &str, args: &[String]) -> String {
+    let mut words = vec![shell_quote(command)];
+    words.extend(args.iter().map(|arg| shell_quote(arg)));
+    words.join(" ")
+}
+
+/// Single quotes the word if it has any characters the shell would interpret.
+fn shell_quote(word: &str) -> String {
+    let is_safe = !word.is_empty() && word.chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if is_safe {
+        return word.to_owned();
+    }
+
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindgen_args_default_to_no_modules() {
+        let args = bindgen_args("my_game.wasm", "my_game", "target/wasm-rgame/my-game", BindgenTarget::NoModules, false);
+        assert_eq!(args.join(" "), "my_game.wasm --no-modules --no-modules-global my_game --no-typescript --out-dir target/wasm-rgame/my-game");
+    }
+
+    #[test]
+    fn bindgen_args_for_web_with_typescript() {
+        let args = bindgen_args("my_game.wasm", "my_game", "target/wasm-rgame/my-game", BindgenTarget::Web, true);
+        assert_eq!(args.join(" "), "my_game.wasm --target web --out-dir target/wasm-rgame/my-game");
+    }
+
+    #[test]
+    fn bindgen_args_for_bundler() {
+        let args = bindgen_args("my_game.wasm", "my_game", "target/wasm-rgame/my-game", BindgenTarget::Bundler, false);
+        assert_eq!(args.join(" "), "my_game.wasm --target bundler --no-typescript --out-dir target/wasm-rgame/my-game");
+    }
+
+    #[test]
+    fn command_line_quotes_args() {
+        let args = bindgen_args("target/my game.wasm", "my_game", "target/wasm-rgame/my-game", BindgenTarget::NoModules, false);
+        assert_eq!(
+            command_line("wasm-bindgen", &args),
+            "wasm-bindgen 'target/my game.wasm' --no-modules --no-modules-global my_game --no-typescript --out-dir target/wasm-rgame/my-game"
+        );
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn parses_bindgen_targets() {
+        assert_eq!("no-modules".parse::<BindgenTarget>(), Ok(BindgenTarget::NoModules));
+        assert_eq!("web".parse::<BindgenTarget>(), Ok(BindgenTarget::Web));
+        assert_eq!("bundler".parse::<BindgenTarget>(), Ok(BindgenTarget::Bundler));
+        assert!("nodejs".parse::<BindgenTarget>().is_err());
+    }
+
+    #[test]
+    fn import_snippet_for_each_target() {
+        assert_eq!(import_snippet("my_game", "my_game", BindgenTarget::NoModules), "<script src=\"./my_game.js\"></script>");
+        assert_eq!(
+            import_snippet("my_game", "my_game", BindgenTarget::Web),
+            "<script type=\"module\">import init, * as exports from \"./my_game.js\"; window.my_game = Object.assign(init, exports);</script>"
+        );
+        assert_eq!(import_snippet("my_game", "my_game", BindgenTarget::Bundler), "import * as my_game from \"./my_game\";");
+    }
+
+    #[test]
+    fn import_snippet_uses_global_name_for_hyphenated_bins() {
+        assert_eq!(import_snippet("level-editor", "level_editor", BindgenTarget::NoModules), "<script src=\"./level-editor.js\"></script>");
+        assert_eq!(
+            import_snippet("level-editor", "level_editor", BindgenTarget::Web),
+            "<script type=\"module\">import init, * as exports from \"./level-editor.js\"; window.level_editor = Object.assign(init, exports);</script>"
+        );
+        assert_eq!(import_snippet("level-editor", "level_editor", BindgenTarget::Bundler), "import * as level_editor from \"./level-editor\";");
+    }
+}
@@ -0,0 +1,112 @@
+use super::*;
+
+/// Lists the names of all files in the directory.
+pub fn file_names_in_dir(dir_path: &Path) -> Result<Vec<String>> {
+    let mut file_names = Vec::new();
+    for entry in fs::read_dir(dir_path)? {
+        if let Ok(file_name) = entry?.file_name().into_string() {
+            file_names.push(file_name);
+        }
+    }
+
+    Ok(file_names)
+}
+
+/// The `.js` / `.wasm` files that were added to the directory between the
+/// two listings, which are the files emitted by wasm-bindgen.
+pub fn emitted_files(before: &[String], after: &[String]) -> Vec<String> {
+    let mut emitted = after.iter()
+        .filter(|file_name| !before.contains(file_name))
+        .filter(|file_name| file_name.ends_with(".js") || file_name.ends_with(".wasm"))
+        .cloned()
+        .collect::<Vec<_>>();
+    emitted.sort();
+    emitted
+}
+
+/// The emitted files that are not referenced by the templated entry file (ex. index.html).
+pub fn unreferenced_files<'a>(index_contents: &str, emitted: &'a [String]) -> Vec<&'a String> {
+    emitted.iter()
+        .filter(|file_name| !index_contents.contains(file_name.as_str()))
+        .collect()
+}
+
+/// Warns if wasm-bindgen's output doesn't match what the templated entry file
+/// (`index_name`, ex. index.html) expects, since the output naming has changed
+/// between wasm-bindgen versions.
+pub fn check_emitted_files(target_dir_path: &Path, before: &[String], index_name: &str) -> Result<()> {
+    let emitted = emitted_files(before, &file_names_in_dir(target_dir_path)?);
+    if emitted.is_empty() {
+        wargo_warn!("Could not find any .js / .wasm files emitted by wasm-bindgen in {:?}!\n", target_dir_path);
+        return Ok(());
+    }
+
+    for file_name in unreferenced_by_index(target_dir_path, &emitted, index_name)? {
+        wargo_warn!("wasm-bindgen emitted `{}` but it is not referenced by {}, \
+               the output naming may not match this version of wasm-bindgen!\n", file_name, index_name);
+    }
+
+    Ok(())
+}
+
+/// The emitted files that the entry file in the directory doesn't reference, none
+/// if there is no entry file (which is warned about after the build).
+fn unreferenced_by_index(target_dir_path: &Path, emitted: &[String], index_name: &str) -> Result<Vec<String>> {
+    let index_path = target_dir_path.join(index_name);
+    if !index_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let index_contents = fs::read_to_string(&index_path)
+        .map_err(|err| format_err!("Failed to read {:?} to check the wasm-bindgen output, error: {}", index_path, err))?;
+    Ok(unreferenced_files(&index_contents, emitted).into_iter().cloned().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn emitted_files_ignores_copied_and_other_files() {
+        let before = names(&["index.html", "wasm-rgame.js"]);
+        let after = names(&["index.html", "wasm-rgame.js", "my_game_bg.wasm", "my_game.js", "my_game.d.ts"]);
+
+        assert_eq!(emitted_files(&before, &after), names(&["my_game.js", "my_game_bg.wasm"]));
+    }
+
+    #[test]
+    fn unreferenced_files_finds_mismatched_names() {
+        let index_contents = r#"<script src="./my_game.js"></script>
+                                <script>wasm_bindgen('./my_game_bg.wasm')</script>"#;
+
+        let emitted = names(&["my_game.js", "my_game_bg.wasm"]);
+        assert!(unreferenced_files(index_contents, &emitted).is_empty());
+
+        let emitted = names(&["my_game.js", "my_game.wasm"]);
+        assert_eq!(unreferenced_files(index_contents, &emitted), vec!["my_game.wasm"]);
+    }
+
+    #[test]
+    fn check_emitted_files_counts_warnings() {
+        let tmp_dir = TempDir::new().unwrap();
+
+        let count_before = warning_count();
+        check_emitted_files(tmp_dir.path(), &[], "index.html").unwrap();
+        assert!(warning_count() > count_before);
+    }
+
+    #[test]
+    fn unreferenced_by_index_reads_custom_index_name() {
+        let tmp_dir = TempDir::new().unwrap();
+        fs::write(tmp_dir.path().join("game.html"), r#"<script src="./my_game.js"></script>"#).unwrap();
+        let emitted = names(&["my_game.js", "my_game_bg.wasm"]);
+
+        assert_eq!(unreferenced_by_index(tmp_dir.path(), &emitted, "game.html").unwrap(), names(&["my_game_bg.wasm"]));
+        // index.html was never emitted, so there is nothing to check against
+        assert!(unreferenced_by_index(tmp_dir.path(), &emitted, "index.html").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,334 @@
+use super::*;
+
+/// The directory cargo looks for example targets in.
+const EXAMPLES_DIR : &'static str = "examples";
+
+/// The cargo target that is built and bundled.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildTarget {
+    Lib,
+    Bin(String),
+    Example(String),
+}
+
+impl BuildTarget {
+    /// Chooses the target from the `--lib` / `--bin` / `--example` flags, defaulting
+    /// to the lib (or the only target) when it isn't ambiguous.
+    pub fn select(cargo_toml: &CargoToml, has_lib: bool, lib: bool, bin: Option<String>, example: Option<String>) -> Result<BuildTarget> {
+        if let Some(example) = example {
+            if lib || bin.is_some() {
+                return Err(format_err!("`--example` can't be used together with `--lib` or `--bin`!"));
+            }
+
+            if !example_exists(Path::new(EXAMPLES_DIR), &example) {
+                return Err(format_err!("No example named `{}` found in the `{}` directory!", example, EXAMPLES_DIR));
+            }
+
+            return Ok(BuildTarget::Example(example));
+        }
+
+        let bin_names = cargo_toml.bin_names();
+        match (lib, bin) {
+            (true, Some(_)) => Err(format_err!("Only one of `--lib` and `--bin` can be used at a time!")),
+            (true, None) => {
+                if !has_lib {
+                    return Err(format_err!("`--lib` was passed but the project has no lib target!"));
+                }
+
+                Ok(BuildTarget::Lib)
+            },
+            (false, Some(bin)) => {
+                if !bin_names.contains(&bin.as_str()) {
+                    return Err(format_err!("No bin target named `{}`, the bin targets are: {:?}", bin, bin_names));
+                }
+
+                Ok(BuildTarget::Bin(bin))
+            },
+            (false, None) => {
+                match (has_lib, bin_names.len()) {
+                    (_, 0) => Ok(BuildTarget::Lib),
+                    (false, 1) => Ok(BuildTarget::Bin(bin_names[0].to_owned())),
+                    _ => Err(format_err!("The project has multiple targets, choose which one to build with \
+                                          `--lib` or `--bin <name>` (bin targets: {:?})", bin_names)),
+                }
+            },
+        }
+    }
+
+    /// The lib (if there is one) and bin targets of the project.
+    pub fn all(cargo_toml: &CargoToml, has_lib: bool) -> Vec<BuildTarget> {
+        let mut targets = Vec::new();
+        if has_lib {
+            targets.push(BuildTarget::Lib);
+        }
+        targets.extend(cargo_toml.bin_names().into_iter().map(|name| BuildTarget::Bin(name.to_owned())));
+        targets
+    }
+
+    /// The arguments that select this target for `cargo build`.
+    pub fn cargo_args(&self) -> Vec<String> {
+        match *self {
+            BuildTarget::Lib => vec!["--lib".to_owned()],
+            BuildTarget::Bin(ref name) => vec!["--bin".to_owned(), name.clone()],
+            BuildTarget::Example(ref name) => vec!["--example".to_owned(), name.clone()],
+        }
+    }
+
+    /// The name of the built wasm file (without the extension), which is also
+    /// used as the name of the wasm-bindgen output. Cargo keeps the hyphens of
+    /// binaries, examples are binaries unless they set a library `crate-type`.
+    pub fn built_name(&self, cargo_toml: &CargoToml) -> String {
+        match *self {
+            BuildTarget::Lib => cargo_toml.lib_name(),
+            BuildTarget::Bin(ref name) => name.clone(),
+            BuildTarget::Example(ref name) if cargo_toml.is_lib_example(name) => ::cargo_toml::crate_name(name),
+            BuildTarget::Example(ref name) => name.clone(),
+        }
+    }
+
+    /// The name of the JS global the wasm-bindgen output is exposed as (and substituted
+    /// for `$PROJECT_NAME`), normalized like a crate name since the built name of bins
+    /// and examples can have hyphens, which aren't valid in a JS identifier.
+    pub fn js_global_name(&self, cargo_toml: &CargoToml) -> String {
+        ::cargo_toml::crate_name(&self.built_name(cargo_toml))
+    }
+
+    /// The path of the built wasm file in the profile's output directory, examples
+    /// are output to their own `examples` directory by cargo.
+    pub fn wasm_output_path(&self, cargo_toml: &CargoToml, profile_dir: &str) -> String {
+        let built_name = self.built_name(cargo_toml);
+        match *self {
+            BuildTarget::Example(_) => format!("target/wasm32-unknown-unknown/{}/examples/{}.wasm", profile_dir, built_name),
+            _ => format!("target/wasm32-unknown-unknown/{}/{}.wasm", profile_dir, built_name),
+        }
+    }
+
+    /// The sources cargo rebuilds the target from, the example's own sources are
+    /// only part of the example target.
+    pub fn source_paths(&self) -> Vec<PathBuf> {
+        match *self {
+            BuildTarget::Example(ref name) => vec![
+                PathBuf::from("src"),
+                Path::new("examples").join(format!("{}.rs", name)),
+                Path::new("examples").join(name),
+            ],
+            _ => vec![PathBuf::from("src")],
+        }
+    }
+
+    /// The name of the wasm-rgame output directory, bins and examples get their
+    /// own directory so they don't overwrite the lib's bundle.
+    pub fn output_dir_name(&self, project_name: &str) -> String {
+        match *self {
+            BuildTarget::Lib => project_name.to_owned(),
+            BuildTarget::Bin(ref name) | BuildTarget::Example(ref name) => name.clone(),
+        }
+    }
+}
+
+/// Errors if another of the targets would be built to the same wasm file as the
+/// target (ex. a lib `my-game` and a bin `my_game`) or bundled into the same output
+/// directory (ex. a lib and an example both named `my-game`), which would silently
+/// overwrite each other's output.
+pub fn check_output_collisions(target: &BuildTarget, other_targets: &[BuildTarget], cargo_toml: &CargoToml, profile_dir: &str) -> Result<()> {
+    let project_name = &cargo_toml.package.name;
+    let wasm_output_path = target.wasm_output_path(cargo_toml, profile_dir);
+    let output_dir_name = target.output_dir_name(project_name);
+    for other_target in other_targets.iter().filter(|other_target| *other_target != target) {
+        if other_target.wasm_output_path(cargo_toml, profile_dir) == wasm_output_path {
+            return Err(format_err!("The {:?} and {:?} targets are both built to `{}`, rename one of them so \
+                                    they don't overwrite each other!", target, other_target, wasm_output_path));
+        }
+
+        if other_target.output_dir_name(project_name) == output_dir_name {
+            return Err(format_err!("The {:?} and {:?} targets are both bundled into `{}/{}`, rename one of them so \
+                                    they don't overwrite each other!", target, other_target, OUTPUT_ROOT, output_dir_name));
+        }
+    }
+
+    Ok(())
+}
+
+/// An example is either a single `examples/<name>.rs` file or a
+/// `examples/<name>/main.rs` directory.
+fn example_exists(examples_dir: &Path, name: &str) -> bool {
+    examples_dir.join(format!("{}.rs", name)).is_file() || examples_dir.join(name).join("main.rs").is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn multiple_targets_toml() -> CargoToml {
+        CargoToml::from_str(r#"
+            [package]
+            name = "my-game"
+
+            [[bin]]
+            name = "level-editor"
+        "#).unwrap()
+    }
+
+    #[test]
+    fn select_defaults_to_lib() {
+        let cargo_toml = CargoToml::from_str("[package]\nname = \"my-game\"\n").unwrap();
+
+        let target = BuildTarget::select(&cargo_toml, true, false, None, None).unwrap();
+        assert_eq!(target, BuildTarget::Lib);
+        assert_eq!(target.cargo_args(), vec!["--lib"]);
+        assert_eq!(target.built_name(&cargo_toml), "my_game");
+    }
+
+    #[test]
+    fn select_errors_when_ambiguous() {
+        assert!(BuildTarget::select(&multiple_targets_toml(), true, false, None, None).is_err());
+    }
+
+    #[test]
+    fn select_uses_only_bin() {
+        let target = BuildTarget::select(&multiple_targets_toml(), false, false, None, None).unwrap();
+        assert_eq!(target, BuildTarget::Bin("level-editor".to_owned()));
+    }
+
+    #[test]
+    fn select_chosen_bin() {
+        let cargo_toml = multiple_targets_toml();
+
+        let target = BuildTarget::select(&cargo_toml, true, false, Some("level-editor".to_owned()), None).unwrap();
+        assert_eq!(target.cargo_args(), vec!["--bin", "level-editor"]);
+        assert_eq!(target.built_name(&cargo_toml), "level-editor");
+
+        assert!(BuildTarget::select(&cargo_toml, true, false, Some("missing".to_owned()), None).is_err());
+    }
+
+    #[test]
+    fn hyphenated_bin_keeps_wasm_name_but_normalizes_global() {
+        let cargo_toml = multiple_targets_toml();
+        let target = BuildTarget::Bin("level-editor".to_owned());
+
+        let wasm_output_path = target.wasm_output_path(&cargo_toml, "debug");
+        assert_eq!(wasm_output_path, "target/wasm32-unknown-unknown/debug/level-editor.wasm");
+        assert_eq!(target.js_global_name(&cargo_toml), "level_editor");
+
+        let args = bindgen::bindgen_args(&wasm_output_path, &target.js_global_name(&cargo_toml), "target/wasm-rgame/my-game", BindgenTarget::NoModules, false);
+        assert_eq!(args[1..4].join(" "), "--no-modules --no-modules-global level_editor");
+    }
+
+    #[test]
+    fn select_lib_requires_lib_target() {
+        assert_eq!(BuildTarget::select(&multiple_targets_toml(), true, true, None, None).unwrap(), BuildTarget::Lib);
+        assert!(BuildTarget::select(&multiple_targets_toml(), false, true, None, None).is_err());
+        assert!(BuildTarget::select(&multiple_targets_toml(), true, true, Some("level-editor".to_owned()), None).is_err());
+    }
+
+    #[test]
+    fn select_example_conflicts_with_other_targets() {
+        let example = Some("bouncing-balls".to_owned());
+        assert!(BuildTarget::select(&multiple_targets_toml(), true, true, None, example.clone()).is_err());
+        assert!(BuildTarget::select(&multiple_targets_toml(), true, false, Some("level-editor".to_owned()), example).is_err());
+    }
+
+    #[test]
+    fn example_paths() {
+        let cargo_toml = multiple_targets_toml();
+        let target = BuildTarget::Example("bouncing-balls".to_owned());
+
+        assert_eq!(target.cargo_args(), vec!["--example", "bouncing-balls"]);
+        assert_eq!(target.wasm_output_path(&cargo_toml, "debug"), "target/wasm32-unknown-unknown/debug/examples/bouncing-balls.wasm");
+        assert_eq!(target.js_global_name(&cargo_toml), "bouncing_balls");
+        assert_eq!(target.output_dir_name("my-game"), "bouncing-balls");
+
+        let cargo_toml = CargoToml::from_str(r#"
+            [package]
+            name = "my-game"
+
+            [[example]]
+            name = "bouncing-balls"
+            crate-type = ["cdylib"]
+        "#).unwrap();
+        assert_eq!(target.wasm_output_path(&cargo_toml, "debug"), "target/wasm32-unknown-unknown/debug/examples/bouncing_balls.wasm");
+
+        assert_eq!(BuildTarget::Lib.wasm_output_path(&cargo_toml, "release-wasm"), "target/wasm32-unknown-unknown/release-wasm/my_game.wasm");
+        assert_eq!(BuildTarget::Lib.output_dir_name("my-game"), "my-game");
+        assert_eq!(BuildTarget::Bin("level-editor".to_owned()).output_dir_name("my-game"), "level-editor");
+    }
+
+    #[test]
+    fn mixed_case_and_hyphenated_names_agree() {
+        let cargo_toml = CargoToml::from_str(r#"
+            [package]
+            name = "My-Game"
+        "#).unwrap();
+
+        // The wasm file and the wasm-bindgen global are both named after the built name
+        assert_eq!(BuildTarget::Lib.built_name(&cargo_toml), "My_Game");
+        assert_eq!(BuildTarget::Lib.wasm_output_path(&cargo_toml, "debug"), "target/wasm32-unknown-unknown/debug/My_Game.wasm");
+        assert_eq!(BuildTarget::Lib.output_dir_name(&cargo_toml.package.name), "My-Game");
+
+        let target = BuildTarget::Example("Bouncing-Balls".to_owned());
+        assert_eq!(target.wasm_output_path(&cargo_toml, "debug"), "target/wasm32-unknown-unknown/debug/examples/Bouncing-Balls.wasm");
+        assert_eq!(target.js_global_name(&cargo_toml), "Bouncing_Balls");
+    }
+
+    #[test]
+    fn source_paths_only_include_the_targets_example() {
+        assert_eq!(BuildTarget::Lib.source_paths(), vec![PathBuf::from("src")]);
+        assert_eq!(BuildTarget::Bin("level-editor".to_owned()).source_paths(), vec![PathBuf::from("src")]);
+        assert_eq!(
+            BuildTarget::Example("bouncing-balls".to_owned()).source_paths(),
+            vec![PathBuf::from("src"), PathBuf::from("examples/bouncing-balls.rs"), PathBuf::from("examples/bouncing-balls")]
+        );
+    }
+
+    #[test]
+    fn example_exists_checks_file_and_directory_forms() {
+        let examples_dir = tempfile::tempdir().unwrap();
+        fs::write(examples_dir.path().join("single.rs"), "fn main() {}").unwrap();
+        fs::create_dir(examples_dir.path().join("multi")).unwrap();
+        fs::write(examples_dir.path().join("multi").join("main.rs"), "fn main() {}").unwrap();
+
+        assert!(example_exists(examples_dir.path(), "single"));
+        assert!(example_exists(examples_dir.path(), "multi"));
+        assert!(!example_exists(examples_dir.path(), "missing"));
+    }
+
+    #[test]
+    fn check_output_collisions_detects_same_built_name() {
+        let cargo_toml = CargoToml::from_str(r#"
+            [package]
+            name = "my-game"
+
+            [[bin]]
+            name = "my_game"
+
+            [[bin]]
+            name = "level-editor"
+        "#).unwrap();
+        let targets = BuildTarget::all(&cargo_toml, true);
+
+        assert!(check_output_collisions(&BuildTarget::Lib, &targets, &cargo_toml, "debug").is_err());
+        assert!(check_output_collisions(&BuildTarget::Bin("my_game".to_owned()), &targets, &cargo_toml, "debug").is_err());
+        assert!(check_output_collisions(&BuildTarget::Bin("level-editor".to_owned()), &targets, &cargo_toml, "debug").is_ok());
+        assert!(check_output_collisions(&BuildTarget::Example("my_game".to_owned()), &targets, &cargo_toml, "debug").is_err());
+        assert!(check_output_collisions(&BuildTarget::Example("bouncing-balls".to_owned()), &targets, &cargo_toml, "debug").is_ok());
+    }
+
+    #[test]
+    fn check_output_collisions_detects_same_output_dir() {
+        let cargo_toml = CargoToml::from_str(r#"
+            [package]
+            name = "my-game"
+
+            [[bin]]
+            name = "my-game"
+        "#).unwrap();
+        let targets = BuildTarget::all(&cargo_toml, true);
+
+        // The wasm files differ (`my_game.wasm` and `my-game.wasm`) but both are bundled into `my-game`
+        assert!(check_output_collisions(&BuildTarget::Lib, &targets, &cargo_toml, "debug").is_err());
+        assert!(check_output_collisions(&BuildTarget::Bin("my-game".to_owned()), &targets, &cargo_toml, "debug").is_err());
+        assert!(check_output_collisions(&BuildTarget::Example("my-game".to_owned()), &targets, &cargo_toml, "debug").is_err());
+        assert!(check_output_collisions(&BuildTarget::Example("my-game".to_owned()), &[BuildTarget::Lib], &cargo_toml, "debug").is_err());
+        assert!(check_output_collisions(&BuildTarget::Example("bouncing-balls".to_owned()), &targets, &cargo_toml, "debug").is_ok());
+    }
+}
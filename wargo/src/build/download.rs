@@ -0,0 +1,266 @@
+use reqwest::{Client, RedirectPolicy};
+use reqwest::header::{qitem, Accept, Authorization, Headers, UserAgent};
+use reqwest::mime;
+
+use super::*;
+
+use std::path::Component;
+
+use release::JsRelease;
+
+/// GitHub redirects release downloads (to S3 for release assets), these are
+/// followed up to this limit.
+const MAX_REDIRECTS : usize = 10;
+
+/// The client used to download the release tarballs, following redirects and
+/// sending the GitHub token (when provided) for private repositories.
+pub fn client(github_token: Option<&str>) -> Result<Client> {
+    let mut headers = Headers::new();
+    headers.set(UserAgent::new("wargo-agent"));
+    if let Some(github_token) = github_token {
+        headers.set(Authorization(format!("token {}", github_token)));
+    }
+
+    Client::builder()
+        .redirect(RedirectPolicy::limited(MAX_REDIRECTS))
+        .default_headers(headers)
+        .build()
+        .map_err(|err| format_err!("Could not create the HTTP client, error: {}", err))
+}
+
+/// Downloads the tarball of the release (or its pre-built bundle asset), retrying
+/// transient network failures.
+pub fn download_release_tarball(release: &JsRelease, repo: &ReleaseRepo, prefer_source: bool) -> Result<Vec<u8>> {
+    let download = tarball_download(release, repo.github_token().is_some(), prefer_source);
+    if download.is_asset {
+        info!("Using the `{}` asset attached to the release.\n", RELEASE_ASSET_NAME);
+    }
+
+    let client = client(repo.github_token())?;
+    let mut res = retry::with_retries(repo.retries, "download the release tarball", || {
+        let mut request = client.get(download.url);
+        if download.is_api_asset {
+            // Without this the API responds with the asset's JSON description
+            request.header(Accept(vec![qitem(mime::APPLICATION_OCTET_STREAM)]));
+        }
+
+        let res = request.send()
+            .map_err(|err| {
+                let is_transient = is_transient_send_error(&err);
+                let err = WargoError::NetworkError { message: format!("Could not download release tarball, error: {}", err), status: None }.into();
+                if is_transient { retry::AttemptError::Transient(err) } else { retry::AttemptError::Permanent(err) }
+            })?;
+
+        let status = res.status();
+        if !status.is_success() {
+            let err = WargoError::NetworkError {
+                message: format!("Could not download release tarball, status: {}", status),
+                status: Some(status.as_u16()),
+            }.into();
+            return Err(if status.is_server_error() { retry::AttemptError::Transient(err) } else { retry::AttemptError::Permanent(err) });
+        }
+
+        Ok(res)
+    })?;
+
+    let mut tarball_bytes = Vec::new();
+    res.read_to_end(&mut tarball_bytes)
+        .map_err(|err| format_err!("Could not read the downloaded release tarball, error: {}", err))?;
+    tarball::validate(&tarball_bytes)?;
+
+    Ok(tarball_bytes)
+}
+
+/// Timeouts and connection failures (the errors of the underlying HTTP connection)
+/// are transient, anything else (ex. too many redirects) won't be fixed by retrying.
+fn is_transient_send_error(err: &reqwest::Error) -> bool {
+    err.is_timeout() || err.is_http()
+}
+
+/// Where the release tarball is downloaded from.
+struct TarballDownload<'a> {
+    url: &'a str,
+    /// The pre-built bundle asset is used instead of the source tarball.
+    is_asset: bool,
+    /// The asset is downloaded through the API, which needs the octet-stream `Accept` header.
+    is_api_asset: bool,
+}
+
+/// The url of the release's bundle asset (unless preferring the source tarball) or
+/// source tarball. GitHub doesn't authenticate the asset's browser download url, so
+/// with a token (for private repositories) the asset is downloaded through the API.
+fn tarball_download<'a>(release: &'a JsRelease, has_token: bool, prefer_source: bool) -> TarballDownload<'a> {
+    let release_asset = release.assets.iter().find(|asset| asset.name == RELEASE_ASSET_NAME);
+    match release_asset {
+        Some(release_asset) if !prefer_source => {
+            let is_api_asset = has_token && !release_asset.url.is_empty();
+            let url = if is_api_asset { &release_asset.url } else { &release_asset.browser_download_url };
+            TarballDownload { url, is_asset: true, is_api_asset }
+        },
+        _ => TarballDownload { url: &release.tarball_url, is_asset: false, is_api_asset: false },
+    }
+}
+
+/// Unpacks the tarball into a temporary directory, returning the directory (removed
+/// when dropped) along with the path of the unpacked release files.
+///
+/// With `strip_components` the leading components of each path in the tarball
+/// are removed, like `tar --strip-components`. Otherwise the single top-level
+/// directory of the tarball (as in GitHub's source tarballs) is used if there is one.
+pub fn unpack_release(tarball_bytes: &[u8], strip_components: Option<usize>) -> Result<(TempDir, PathBuf)> {
+    let unpack_tmp_dir = TempDir::new()
+        .map_err(|err| format_err!("Could not create a temporary directory, error: {}", err))?;
+
+    let decoded_res = GzDecoder::new(tarball_bytes);
+    let mut archive = tar::Archive::new(decoded_res);
+
+    let strip_components = match strip_components {
+        Some(strip_components) => strip_components,
+        None => {
+            archive.unpack(unpack_tmp_dir.path())
+                .map_err(|err| format_err!("Could not unpack archive into the temporary directory, error: {}", err))?;
+
+            let unpacked_dir_path = single_dir_entry(unpack_tmp_dir.path())?
+                .unwrap_or_else(|| unpack_tmp_dir.path().to_path_buf());
+            return Ok((unpack_tmp_dir, unpacked_dir_path));
+        },
+    };
+
+    let entries = archive.entries()
+        .map_err(|err| format_err!("Could not read the entries of the archive, error: {}", err))?;
+    for entry in entries {
+        let mut entry = entry
+            .map_err(|err| format_err!("Could not read an entry of the archive, error: {}", err))?;
+        let entry_path = entry.path()
+            .map_err(|err| format_err!("Could not read the path of an archive entry, error: {}", err))?
+            .into_owned();
+
+        let stripped_path = match strip_path(&entry_path, strip_components) {
+            Some(stripped_path) => stripped_path,
+            None => continue,
+        };
+
+        let unpacked_path = unpack_tmp_dir.path().join(stripped_path);
+        if let Some(parent) = unpacked_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|err| format_err!("Could not create the directory {:?}, error: {}", parent, err))?;
+        }
+        entry.unpack(&unpacked_path)
+            .map_err(|err| format_err!("Could not unpack {:?} from the archive, error: {}", entry_path, err))?;
+    }
+
+    let unpacked_dir_path = unpack_tmp_dir.path().to_path_buf();
+    Ok((unpack_tmp_dir, unpacked_dir_path))
+}
+
+/// The path of the only entry in the directory, if it is a directory.
+fn single_dir_entry(dir_path: &Path) -> Result<Option<PathBuf>> {
+    let entries = fs::read_dir(dir_path)
+        .map_err(|err| format_err!("Failed to read temporary directory, error: {}", err))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|err| format_err!("Failed to read temporary directory entry, error: {}", err))?;
+
+    match entries.as_slice() {
+        [entry] if entry.path().is_dir() => Ok(Some(entry.path())),
+        _ => Ok(None),
+    }
+}
+
+/// The path without its leading components, None if nothing is left or the path
+/// isn't a plain relative path (which could unpack outside of the directory).
+fn strip_path(path: &Path, strip_components: usize) -> Option<PathBuf> {
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(component) => components.push(component),
+            Component::CurDir => (),
+            _ => return None,
+        }
+    }
+
+    if components.len() <= strip_components {
+        return None;
+    }
+
+    Some(components[strip_components..].iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use release::JsReleaseAsset;
+
+    /// A tarball of the files under the `wasm-rgame-js-1a2b3c/` directory, like
+    /// GitHub's source tarballs.
+    fn release_tarball(file_paths: &[&str]) -> Vec<u8> {
+        let tmp_dir = TempDir::new().unwrap();
+        let dir_path = tmp_dir.path().join("release");
+        for file_path in file_paths {
+            let file_path = dir_path.join(file_path);
+            fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+            fs::write(file_path, "").unwrap();
+        }
+
+        let archive_path = tmp_dir.path().join("release.tar.gz");
+        tarball::package(&dir_path, &archive_path, "wasm-rgame-js-1a2b3c").unwrap();
+        fs::read(archive_path).unwrap()
+    }
+
+    fn release_with_asset() -> JsRelease {
+        JsRelease {
+            tag_name: "v0.1.0".to_owned(),
+            tarball_url: "https://api.github.com/repos/DarrenTsung/wasm-rgame-js/tarball/v0.1.0".to_owned(),
+            assets: vec![JsReleaseAsset {
+                name: RELEASE_ASSET_NAME.to_owned(),
+                browser_download_url: "https://github.com/DarrenTsung/wasm-rgame-js/releases/download/v0.1.0/wasm-rgame-js.tar.gz".to_owned(),
+                url: "https://api.github.com/repos/DarrenTsung/wasm-rgame-js/releases/assets/1".to_owned(),
+            }],
+        }
+    }
+
+    #[test]
+    fn tarball_download_uses_api_asset_url_with_token() {
+        let release = release_with_asset();
+
+        let download = tarball_download(&release, true, false);
+        assert_eq!(download.url, "https://api.github.com/repos/DarrenTsung/wasm-rgame-js/releases/assets/1");
+        assert!(download.is_asset && download.is_api_asset);
+
+        let download = tarball_download(&release, false, false);
+        assert_eq!(download.url, release.assets[0].browser_download_url);
+        assert!(download.is_asset && !download.is_api_asset);
+
+        let download = tarball_download(&release, true, true);
+        assert_eq!(download.url, release.tarball_url);
+        assert!(!download.is_asset && !download.is_api_asset);
+    }
+
+    #[test]
+    fn unpack_release_uses_single_top_level_dir() {
+        let (_tmp_dir, unpacked_dir_path) = unpack_release(&release_tarball(&["index.html"]), None).unwrap();
+        assert!(unpacked_dir_path.ends_with("wasm-rgame-js-1a2b3c"));
+        assert!(unpacked_dir_path.join("index.html").exists());
+    }
+
+    #[test]
+    fn unpack_release_strips_components() {
+        let tarball_bytes = release_tarball(&["dist/index.html", "dist/js/app.js"]);
+
+        let (_tmp_dir, unpacked_dir_path) = unpack_release(&tarball_bytes, Some(2)).unwrap();
+        assert!(unpacked_dir_path.join("index.html").exists());
+        assert!(unpacked_dir_path.join("js/app.js").exists());
+
+        let (_tmp_dir, unpacked_dir_path) = unpack_release(&tarball_bytes, Some(0)).unwrap();
+        assert!(unpacked_dir_path.join("wasm-rgame-js-1a2b3c/dist/index.html").exists());
+    }
+
+    #[test]
+    fn strip_path_skips_paths_escaping_the_dir() {
+        assert_eq!(strip_path(Path::new("release/js/app.js"), 1), Some(PathBuf::from("js/app.js")));
+        assert_eq!(strip_path(Path::new("./release/index.html"), 1), Some(PathBuf::from("index.html")));
+        assert_eq!(strip_path(Path::new("release"), 1), None);
+        assert_eq!(strip_path(Path::new("release/../../etc/passwd"), 1), None);
+        assert_eq!(strip_path(Path::new("/etc/passwd"), 0), None);
+    }
+}
@@ -0,0 +1,58 @@
+use super::*;
+
+/// Runs the post-build command with the shell in the output directory, with
+/// the details of the build passed as environment variables.
+pub fn run_post_build(command: &str, output_dir: &Path, project_name: &str) -> Result<()> {
+    let output_dir = output_dir.canonicalize()
+        .map_err(|err| format_err!("Could not find the output directory {:?}, error: {}", output_dir, err))?;
+
+    let status = shell_command(command)
+        .current_dir(&output_dir)
+        .env("WARGO_OUTPUT_DIR", &output_dir)
+        .env("WARGO_PROJECT_NAME", project_name)
+        .env("WARGO_VERSION", env!("CARGO_PKG_VERSION"))
+        .status()
+        .map_err(|error| WargoError::CommandNotExecuted {
+            command: command.to_owned(),
+            context: "Run the post-build command".to_owned(),
+            error,
+        })?;
+
+    if !status.success() {
+        return Err(WargoError::PostBuildFailed { command: command.to_owned(), status: status.to_string() }.into());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn shell_command(command: &str) -> Command {
+    let mut shell_command = Command::new("sh");
+    shell_command.arg("-c").arg(command);
+    shell_command
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut shell_command = Command::new("cmd");
+    shell_command.arg("/C").arg(command);
+    shell_command
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_post_build_passes_environment_and_fails_on_non_zero() {
+        let tmp_dir = TempDir::new().unwrap();
+
+        run_post_build("test \"$WARGO_PROJECT_NAME\" = my-game && test \"$PWD\" = \"$WARGO_OUTPUT_DIR\"", tmp_dir.path(), "my-game").unwrap();
+
+        let err = run_post_build("exit 3", tmp_dir.path(), "my-game").unwrap_err();
+        match err.downcast_ref::<WargoError>() {
+            Some(WargoError::PostBuildFailed { command, .. }) => assert_eq!(command, "exit 3"),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+}
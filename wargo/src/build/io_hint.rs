@@ -0,0 +1,66 @@
+use super::*;
+
+use std::io;
+
+/// The raw OS errors for running out of disk space, which io::ErrorKind doesn't
+/// have a kind for.
+#[cfg(unix)]
+const OUT_OF_SPACE_OS_ERRORS : &'static [i32] = &[28 /* ENOSPC */, 122 /* EDQUOT */];
+#[cfg(windows)]
+const OUT_OF_SPACE_OS_ERRORS : &'static [i32] = &[39 /* ERROR_HANDLE_DISK_FULL */, 112 /* ERROR_DISK_FULL */];
+#[cfg(not(any(unix, windows)))]
+const OUT_OF_SPACE_OS_ERRORS : &'static [i32] = &[];
+
+/// The read-only file system error (EROFS), reported like a permission error.
+#[cfg(unix)]
+const READ_ONLY_OS_ERROR : Option<i32> = Some(30);
+#[cfg(not(unix))]
+const READ_ONLY_OS_ERROR : Option<i32> = None;
+
+/// The error with a hint on how to fix the common filesystem failures (ex. in CI
+/// where the target directory isn't writable), otherwise just the raw error.
+pub fn error(message: String, err: io::Error) -> failure::Error {
+    match hint(&err) {
+        Some(hint) => format_err!("{}, error: {}\n{}", message, err, hint),
+        None => format_err!("{}, error: {}", message, err),
+    }
+}
+
+fn hint(err: &io::Error) -> Option<&'static str> {
+    if err.kind() == io::ErrorKind::PermissionDenied || (err.raw_os_error().is_some() && err.raw_os_error() == READ_ONLY_OS_ERROR) {
+        return Some("The target directory is not writable, check that it is owned by the current user \
+                     (ex. it wasn't created by a build run with `sudo`) and is not on a read-only file system.");
+    }
+
+    match err.raw_os_error() {
+        Some(os_error) if OUT_OF_SPACE_OS_ERRORS.contains(&os_error) => {
+            Some("The disk is out of space, free up some space (ex. with `cargo clean`) and try again.")
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hint_for_permission_denied() {
+        let err = io::Error::new(io::ErrorKind::PermissionDenied, "permission denied");
+        assert!(hint(&err).unwrap().contains("not writable"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hint_for_os_errors() {
+        assert!(hint(&io::Error::from_raw_os_error(28)).unwrap().contains("out of space"));
+        assert!(hint(&io::Error::from_raw_os_error(30)).unwrap().contains("read-only"));
+    }
+
+    #[test]
+    fn no_hint_for_other_errors() {
+        let err = io::Error::new(io::ErrorKind::NotFound, "not found");
+        assert!(hint(&err).is_none());
+        assert_eq!(error("Failed creating wasm-rgame target directory".to_owned(), err).to_string(), "Failed creating wasm-rgame target directory, error: not found");
+    }
+}
@@ -0,0 +1,87 @@
+use super::*;
+
+use std::fs::OpenOptions;
+
+use fs2::FileExt;
+
+/// The lock file in the output root, kept when the output root is cleaned.
+pub const LOCK_FILE_NAME : &'static str = ".wargo-lock";
+
+/// An advisory lock on the output root so concurrent builds of the project (ex. an
+/// editor's auto-build and a manual build) don't remove each other's output. The
+/// lock is released when dropped, or by the OS if wargo exits.
+pub struct BuildLock {
+    file: File,
+}
+
+impl BuildLock {
+    /// Locks the output root, waiting for the build holding the lock to finish.
+    pub fn acquire(output_root: &Path) -> Result<BuildLock> {
+        let file = open_lock_file(output_root)?;
+        if try_lock(&file)? {
+            return Ok(BuildLock { file });
+        }
+
+        info!("Another build of the project is in progress, waiting for it to finish \
+               (pass `--no-lock` to build anyway).. ");
+        file.lock_exclusive()
+            .map_err(|err| format_err!("Failed to lock {:?}, error: {}", output_root.join(LOCK_FILE_NAME), err))?;
+        info!("done!\n");
+
+        Ok(BuildLock { file })
+    }
+
+    /// Locks the output root if no other build holds the lock.
+    #[cfg(test)]
+    fn try_acquire(output_root: &Path) -> Result<Option<BuildLock>> {
+        let file = open_lock_file(output_root)?;
+        if try_lock(&file)? {
+            return Ok(Some(BuildLock { file }));
+        }
+
+        Ok(None)
+    }
+}
+
+impl Drop for BuildLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+/// Whether the file was locked, false if another build holds the lock.
+fn try_lock(file: &File) -> Result<bool> {
+    match file.try_lock_exclusive() {
+        Ok(()) => Ok(true),
+        Err(ref err) if err.kind() == fs2::lock_contended_error().kind() => Ok(false),
+        Err(err) => Err(format_err!("Failed to lock the build, error: {}", err)),
+    }
+}
+
+fn open_lock_file(output_root: &Path) -> Result<File> {
+    fs::create_dir_all(output_root)
+        .map_err(|err| io_hint::error(format!("Failed creating the {:?} directory", output_root), err))?;
+
+    let lock_path = output_root.join(LOCK_FILE_NAME);
+    OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&lock_path)
+        .map_err(|err| io_hint::error(format!("Failed to open the lock file {:?}", lock_path), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_is_exclusive_until_dropped() {
+        let tmp_dir = TempDir::new().unwrap();
+        let output_root = tmp_dir.path().join("target/wasm-rgame");
+
+        let lock = BuildLock::try_acquire(&output_root).unwrap();
+        assert!(lock.is_some());
+        assert!(output_root.join(LOCK_FILE_NAME).exists());
+        assert!(BuildLock::try_acquire(&output_root).unwrap().is_none());
+
+        drop(lock);
+        assert!(BuildLock::try_acquire(&output_root).unwrap().is_some());
+    }
+}
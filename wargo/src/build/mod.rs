@@ -1,22 +1,206 @@
 use super::*;
 
+use std::fmt;
 use std::fs::DirEntry;
+use std::io;
+use std::path::Component;
+use std::time::Instant;
 
-mod choose_version;
-use self::choose_version::choose_version_by_key;
+mod bindgen;
+pub use self::bindgen::BindgenTarget;
+
+mod bindgen_output;
+
+mod download;
+pub use self::download::{download_release_tarball, unpack_release};
+
+mod git;
+
+mod hook;
+
+mod io_hint;
+
+mod lock;
+use self::lock::BuildLock;
+
+mod build_target;
+use self::build_target::{BuildTarget, check_output_collisions};
+
+mod profile;
+
+mod rustup;
+pub use self::rustup::check_wasm_target_installed;
+
+mod staleness;
+
+mod tarball;
+
+/// The directory the output of every project / example is put in.
+const OUTPUT_ROOT : &'static str = "target/wasm-rgame";
+
+/// Name of the pre-built bundle that can be attached to a wasm-rgame-js release,
+/// preferred over the source tarball when present.
+const RELEASE_ASSET_NAME : &'static str = "wasm-rgame-js.tar.gz";
+
+mod timings;
+use self::timings::Timings;
 
 pub struct BuildProjectConfig {
     pub js_path: Option<PathBuf>,
+    pub js_version: Option<String>,
+    pub progress: ProgressMode,
+    pub timings: bool,
+    pub keep_temp: bool,
+    pub retries: u32,
+    pub prefer_source: bool,
+    /// How many leading path components to strip when unpacking the release, by
+    /// default the single top-level directory of the tarball is used.
+    pub strip_components: Option<usize>,
+    /// Fetch the release with `git clone` instead of the GitHub API and tarballs.
+    pub use_git: bool,
+    pub bindgen_target: BindgenTarget,
+    pub typescript: bool,
+    /// The GitHub repository to download the js releases from, ex. "DarrenTsung/wasm-rgame-js".
+    pub js_repo: String,
+    /// The API url of a GitHub Enterprise host, defaults to github.com.
+    pub github_host: Option<String>,
+    pub github_token: Option<String>,
+    /// Create a .tar.gz of the output directory after building.
+    pub package: bool,
+    /// Build the lib target, the default unless the project has `[[bin]]` targets.
+    pub lib: bool,
+    /// Build the named `[[bin]]` target instead of the lib.
+    pub bin: Option<String>,
+    /// Build the named example from the `examples/` directory instead of the lib.
+    pub example: Option<String>,
+    /// The cargo profile to build with, defaults to the `dev` profile.
+    pub profile: Option<String>,
+    /// Skip the rustup setup and cargo build, bundling the already built wasm.
+    pub no_build: bool,
+    /// Fail the build if any warnings were emitted.
+    pub deny_warnings: bool,
+    /// The entry HTML file of the bundled project, ex. "index.html".
+    pub index_name: String,
+    /// Fetch the releases even if there is a fresh cached releases list.
+    pub refresh_releases: bool,
+    /// Pass `--locked` to cargo, failing the build if the Cargo.lock would change.
+    pub locked: bool,
+    /// Pass `--frozen` to cargo, like `--locked` but also without network access.
+    pub frozen: bool,
+    /// A shell command to run in the output directory after a successful build.
+    pub post_build: Option<String>,
+    /// Stop after the cargo build and print the wasm-bindgen command instead of running it.
+    pub print_bindgen_cmd: bool,
+    /// Remove the whole `target/wasm-rgame` directory instead of only the project's output.
+    pub force_clean: bool,
+    /// Build without locking `target/wasm-rgame`, allowing concurrent builds of the project.
+    pub no_lock: bool,
+}
+
+/// The same defaults as `wargo build` without any flags or wargo.toml.
+impl Default for BuildProjectConfig {
+    fn default() -> BuildProjectConfig {
+        BuildProjectConfig {
+            js_path: None,
+            js_version: None,
+            progress: ProgressMode::Auto,
+            timings: false,
+            keep_temp: false,
+            retries: DEFAULT_RETRIES,
+            prefer_source: false,
+            strip_components: None,
+            use_git: false,
+            bindgen_target: BindgenTarget::NoModules,
+            typescript: false,
+            js_repo: release::DEFAULT_JS_REPO.to_owned(),
+            github_host: None,
+            github_token: None,
+            package: false,
+            lib: false,
+            bin: None,
+            example: None,
+            profile: None,
+            no_build: false,
+            deny_warnings: false,
+            index_name: "index.html".to_owned(),
+            refresh_releases: false,
+            locked: false,
+            frozen: false,
+            post_build: None,
+            print_bindgen_cmd: false,
+            force_clean: false,
+            no_lock: false,
+        }
+    }
+}
+
+impl BuildProjectConfig {
+    fn release_repo(&self) -> ReleaseRepo {
+        ReleaseRepo {
+            repo: self.js_repo.clone(),
+            github_host: self.github_host.clone(),
+            credentials: self.github_token.clone().map(Credentials::Token),
+            retries: self.retries,
+            cache_path: Some(PathBuf::from(release::RELEASES_CACHE_PATH)),
+            refresh: self.refresh_releases,
+        }
+    }
+}
+
+impl fmt::Debug for BuildProjectConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The token is only shown as being set so it doesn't end up in bug reports
+        f.debug_struct("BuildProjectConfig")
+            .field("js_path", &self.js_path)
+            .field("js_version", &self.js_version)
+            .field("progress", &self.progress)
+            .field("timings", &self.timings)
+            .field("keep_temp", &self.keep_temp)
+            .field("retries", &self.retries)
+            .field("prefer_source", &self.prefer_source)
+            .field("strip_components", &self.strip_components)
+            .field("use_git", &self.use_git)
+            .field("bindgen_target", &self.bindgen_target)
+            .field("typescript", &self.typescript)
+            .field("js_repo", &self.js_repo)
+            .field("github_host", &self.github_host)
+            .field("github_token", &self.github_token.as_ref().map(|_| "<hidden>"))
+            .field("package", &self.package)
+            .field("lib", &self.lib)
+            .field("bin", &self.bin)
+            .field("example", &self.example)
+            .field("profile", &self.profile)
+            .field("no_build", &self.no_build)
+            .field("deny_warnings", &self.deny_warnings)
+            .field("index_name", &self.index_name)
+            .field("refresh_releases", &self.refresh_releases)
+            .field("locked", &self.locked)
+            .field("frozen", &self.frozen)
+            .field("post_build", &self.post_build)
+            .field("print_bindgen_cmd", &self.print_bindgen_cmd)
+            .field("force_clean", &self.force_clean)
+            .field("no_lock", &self.no_lock)
+            .finish()
+    }
 }
 
 pub fn build_project(config: BuildProjectConfig) -> Result<()> {
-    if let Some(js_path) = config.js_path {
-        build_project_delegate(|| check_and_use_js_path(js_path))
+    reset_warning_count();
+    debug!("Resolved build configuration: {:#?}\n", config);
+
+    if let Some(ref js_path) = config.js_path {
+        build_project_delegate(&config, || check_and_use_js_path(js_path.clone()))
     } else {
-        build_project_delegate(download_matching_release)
+        build_project_delegate(&config, || download_matching_release(&config))
     }
 }
 
+/// Returns true for the unauthorized, forbidden and not found statuses, which
+/// mean the token can't access the repository (or there is no such repository).
+fn is_access_denied(status: Option<u16>) -> bool {
+    [Some(401), Some(403), Some(404)].contains(&status)
+}
+
 struct ShouldCleanup(bool);
 
 fn check_and_use_js_path(js_path: PathBuf) -> Result<(PathBuf, ShouldCleanup)> {
@@ -29,141 +213,206 @@ fn check_and_use_js_path(js_path: PathBuf) -> Result<(PathBuf, ShouldCleanup)> {
     Ok((js_path, ShouldCleanup(false)))
 }
 
-fn download_matching_release() -> Result<(PathBuf, ShouldCleanup)> {
+fn download_matching_release(config: &BuildProjectConfig) -> Result<(PathBuf, ShouldCleanup)> {
+    ensure_cargo_lock()?;
     let wasm_rgame_version = wasm_rgame_version()?;
     info!("The current project is using wasm-rgame version: `{}`.\n", wasm_rgame_version);
 
     // Download the release of wasm-rgame-js that corresponds to the version of
-    // wasm-rgame that the project is using
-    let mut core = Core::new().unwrap();
-    let github = Github::new("wargo-agent".to_string(), None, &core.handle());
-    let repo_releases = github.repo("DarrenTsung", "wasm-rgame-js").releases();
-    let releases = core.run(repo_releases.list()).unwrap();
-    if releases.is_empty() {
-        return Err(format_err!("Found no releases for wasm-rgame-js!"));
-    }
+    // wasm-rgame that the project is using (or the one explicitly requested)
+    let release_repo = config.release_repo();
+    let js_version = config.js_version.as_ref().map(|v| v.as_str());
+    let (_download_tmp_dir, release_dir_path) = if config.use_git {
+        clone_matching_release(&wasm_rgame_version, js_version, &release_repo)?
+    } else {
+        match download_matching_tarball(&wasm_rgame_version, js_version, &release_repo, config.prefer_source, config.strip_components) {
+            Ok(download) => download,
+            Err(err) => match err.downcast::<WargoError>() {
+                // Git wouldn't be allowed to access the repository either
+                Ok(WargoError::NetworkError { message, status }) if !is_access_denied(status) => {
+                    wargo_warn!("{}\nFalling back to fetching the release with `git`.\n", message);
+                    clone_matching_release(&wasm_rgame_version, js_version, &release_repo)?
+                },
+                Ok(err) => return Err(err.into()),
+                Err(err) => return Err(err),
+            },
+        }
+    };
+
+    // Convert to path, cleanup must be done manually now
+    let final_tmp_path = TempDir::new()?.into_path();
 
-    let chosen_release = choose_version_by_key(wasm_rgame_version, releases, |r| {
-        // Tags look like: "v0.1.0", need to become "0.1.0"
-        let version_str = r.tag_name.split("v").nth(1).unwrap();
-        Version::parse(version_str).ok()
-    });
+    for_each_file_in_dir(&release_dir_path, |dir_entry, file_name| {
+        let new_path = final_tmp_path.join(file_name);
+        create_parent_dir(&new_path)?;
+
+        fs::copy(dir_entry.path(), &new_path)
+            .map_err(|err| format_err!("Failed to copy over unpacked data (from: {:?}, to: {:?}), error: {}", dir_entry.path(), new_path, err))?;
 
-    if chosen_release.is_none() {
-        return Err(format_err!("Found no valid releases for wasm-rgame version!"));
+        Ok(())
+    })?;
+
+    if config.keep_temp {
+        info!("Keeping the downloaded release files at: {:?}\n", final_tmp_path);
     }
 
-    let chosen_release = chosen_release.unwrap();
+    Ok((final_tmp_path, ShouldCleanup(!config.keep_temp)))
+}
+
+/// Finds the matching release with the GitHub API and downloads + unpacks its tarball.
+fn download_matching_tarball(
+    wasm_rgame_version: &Version,
+    js_version: Option<&str>,
+    release_repo: &ReleaseRepo,
+    prefer_source: bool,
+    strip_components: Option<usize>,
+) -> Result<(TempDir, PathBuf)> {
+    let chosen_release = release::fetch_matching_release(wasm_rgame_version, js_version, release_repo)?;
     info!("Found valid release version `{}` for wasm-rgame-js!\n", chosen_release.tag_name);
 
-    let res = reqwest::get(chosen_release.tarball_url.as_str())
-        .map_err(|err| format_err!("Could not download release tarball, error: {}", err))?;
+    let tarball_bytes = download::download_release_tarball(&chosen_release, release_repo, prefer_source)?;
+    download::unpack_release(&tarball_bytes, strip_components)
+}
 
-    let unpack_tmp_dir = TempDir::new()
-        .map_err(|err| format_err!("Could not create a temporary directory, error: {}", err))?;
+/// Finds the matching release among the tags of the repository and shallow clones it.
+fn clone_matching_release(wasm_rgame_version: &Version, js_version: Option<&str>, release_repo: &ReleaseRepo) -> Result<(TempDir, PathBuf)> {
+    let releases = git::fetch_releases(release_repo)?;
+    let chosen_release = release::select_release(wasm_rgame_version, js_version, releases)?;
+    info!("Found valid release version `{}` for wasm-rgame-js!\n", chosen_release.tag_name);
 
-    let decoded_res = GzDecoder::new(res);
-    let mut archive = tar::Archive::new(decoded_res);
-    archive.unpack(unpack_tmp_dir.path())
-        .map_err(|err| format_err!("Could not unpack archive into the temporary directory, error: {}", err))?;
+    git::clone_release(&chosen_release, release_repo)
+}
 
-    // Convert to path, cleanup must be done manually now
-    let final_tmp_path = TempDir::new()?.into_path();
+fn build_project_delegate(config: &BuildProjectConfig, js_path_delegate : impl FnOnce() -> Result<(PathBuf, ShouldCleanup)>) -> Result<()> {
+    let mut timings = Timings::new();
 
-    let unpacked_dir_path = {
-        // Because it dumped the contents into some directory inside the temporary directory
-        // there should only be one entry (which is the unpacked_dir_path)
-        fs::read_dir(unpack_tmp_dir.path())
-            .map_err(|err| format_err!("Failed to read temporary directory, error: {}", err))?
-            .nth(0).expect("one entry exists").expect("can read entry").path()
+    // Held until the build returns, so concurrent builds wait for each other
+    let _build_lock = if config.no_lock {
+        None
+    } else {
+        Some(BuildLock::acquire(Path::new(OUTPUT_ROOT))?)
     };
 
-    for_each_file_in_dir(&unpacked_dir_path, |dir_entry, file_name| {
-        let new_path = final_tmp_path.join(file_name);
+    let cargo_toml = cargo_toml()?;
+    let project_name = cargo_toml.package.name.clone();
+    let has_lib = cargo_toml.lib.is_some() || Path::new("src/lib.rs").exists();
+    let build_target = BuildTarget::select(&cargo_toml, has_lib, config.lib, config.bin.clone(), config.example.clone())?;
+    let built_project_name = build_target.built_name(&cargo_toml);
+    let js_global_name = build_target.js_global_name(&cargo_toml);
+    let profile = config.profile.as_ref().map(|p| p.as_str());
+    let profile_dir = profile::output_dir(profile);
+    check_output_collisions(&build_target, &BuildTarget::all(&cargo_toml, has_lib), &cargo_toml, profile_dir)?;
+    if build_target == BuildTarget::Lib {
+        check_cdylib_crate_type()?;
+    }
 
-        fs::copy(dir_entry.path(), &new_path)
-            .map_err(|err| format_err!("Failed to copy over unpacked data (from: {:?}, to: {:?}), error: {}", dir_entry.path(), new_path, err))?;
+    if !config.no_build {
+        let phase_start = Instant::now();
+        info!("Installing nightly if necessary.. ");
+        execute_command(
+            &tool::rustup(),
+            "toolchain install nightly",
+            "Ensure that the nightly compiler is installed"
+        )?;
+        info!("done!\n");
+
+        info!("Setting override to nightly if necessary.. ");
+        execute_command(
+            &tool::rustup(),
+            "override set nightly",
+            "Ensure that nightly compiler is used for the project"
+        )?;
+        info!("done!\n");
+
+        // Installed after the override so the target is added to the project's toolchain
+        info!("Installing wasm32-unknown-unknown target if necessary.. ");
+        execute_command(
+            &tool::rustup(),
+            "target install wasm32-unknown-unknown",
+            "Ensure that the wasm32-unknown-unknown target is installed"
+        )?;
+        rustup::check_wasm_target_installed()?;
+        info!("done!\n");
+        timings.record("rustup setup", phase_start);
+
+        // Execute the build before cleaning the target directory
+        let phase_start = Instant::now();
+        progress::run_step(config.progress, "Building the project, this may take some time", || {
+            execute_command_args(
+                &tool::cargo(),
+                &cargo_build_args(&build_target, profile, config.locked, config.frozen),
+                "Build project targeting wasm32-unknown-unknown"
+            )
+        })?;
+        timings.record("cargo build", phase_start);
+    }
 
-        Ok(())
-    })?;
+    let wasm_output_path = build_target.wasm_output_path(&cargo_toml, profile_dir);
+    if !Path::new(&wasm_output_path).exists() {
+        if config.no_build {
+            return Err(WargoError::WasmNotBuilt { path: wasm_output_path }.into());
+        }
 
-    Ok((final_tmp_path, ShouldCleanup(true)))
-}
-
-fn build_project_delegate(js_path_delegate : impl FnOnce() -> Result<(PathBuf, ShouldCleanup)>) -> Result<()> {
-    let project_name = project_name()?;
-    let built_project_name = built_project_name(&project_name);
-
-    info!("Installing wasm32-unknown-unknown target if necessary.. ");
-    execute_command(
-        "rustup",
-        "target install wasm32-unknown-unknown",
-        "Ensure that the wasm32-unknown-unknown target is installed"
-    )?;
-    info!("done!\n");
-
-    info!("Installing nightly if necessary.. ");
-    execute_command(
-        "rustup",
-        "toolchain install nightly",
-        "Ensure that the nightly compiler is installed"
-    )?;
-    info!("done!\n");
-
-    info!("Setting override to nightly if necessary.. ");
-    execute_command(
-        "rustup",
-        "override set nightly",
-        "Ensure that nightly compiler is used for the project"
-    )?;
-    info!("done!\n");
-
-    info!("Building the project, this may take some time.. ");
-    // Execute the build before cleaning the target directory
-    execute_command(
-        "cargo",
-        "build --target wasm32-unknown-unknown",
-        "Build project targeting wasm32-unknown-unknown"
-    )?;
-    info!("done!\n");
+        return Err(WargoError::WasmNotFound { path: wasm_output_path, profile_dir: profile_dir.to_owned() }.into());
+    }
+
+    staleness::check_wasm_is_fresh(Path::new(&wasm_output_path), &build_target.source_paths());
 
+    let output_dir_name = build_target.output_dir_name(&project_name);
+    let target_dir = output_dir_path(Path::new(OUTPUT_ROOT), &output_dir_name)?.to_string_lossy().into_owned();
+    let bindgen_args = bindgen::bindgen_args(&wasm_output_path, &js_global_name, &target_dir, config.bindgen_target, config.typescript);
+    if config.print_bindgen_cmd {
+        println!("{}", bindgen::command_line(&tool::wasm_bindgen(), &bindgen_args));
+        return Ok(());
+    }
+
+    let phase_start = Instant::now();
     let (js_path, should_cleanup) = js_path_delegate()?;
+    timings.record("download", phase_start);
+
+    let phase_start = Instant::now();
 
     // Cleanup and create the wasm-rgame target directory
     // The unpacked data specified with the data_path will be added to this clean directory.
-    let target_dir = format!("target/wasm-rgame/{}", project_name);
     let target_dir_path = Path::new(&target_dir);
-    if target_dir_path.exists() {
-        fs::remove_dir_all(target_dir_path)
-            .map_err(|err| format_err!("Failed removing existing wasm-rgame target directory, error: {}", err))?;
+    if config.force_clean {
+        info!("Removing the output of all projects in {}.\n", OUTPUT_ROOT);
+        remove_output_root(Path::new(OUTPUT_ROOT))?;
     }
+    recreate_target_dir(target_dir_path)?;
 
-    fs::create_dir_all(target_dir_path)
-        .map_err(|err| format_err!("Failed creating wasm-rgame target directory, error: {}", err))?;
+    let bindgen_import = bindgen::import_snippet(&built_project_name, &js_global_name, config.bindgen_target);
 
     // Copy over unpacked data to target directory
     for_each_file_in_dir(&js_path, |dir_entry, file_name| {
         let target_entry_path = target_dir_path.join(file_name);
+        create_parent_dir(&target_entry_path)?;
 
         fs::copy(dir_entry.path(), &target_entry_path)
-            .map_err(|err| format_err!("Failed to copy over unpacked data (from: {:?}, to: {:?}), error: {}", dir_entry.path(), target_entry_path, err))?;
+            .map_err(|err| io_hint::error(format!("Failed to copy over unpacked data (from: {:?}, to: {:?})", dir_entry.path(), target_entry_path), err))?;
 
         // Now that the copied file exists, we need to configure it for the project
         let new_file_contents = {
             let mut target_entry_file = File::open(target_entry_path.clone())
-                .map_err(|err| format_err!("Failed to open newly created copy of unpacked data, error: {}", err))?;
+                .map_err(|err| io_hint::error("Failed to open newly created copy of unpacked data".to_owned(), err))?;
 
             let mut file_contents = String::new();
-            target_entry_file.read_to_string(&mut file_contents)
-                .map_err(|err| format_err!("Failed to read newly created copy of unpacked data for: {:?}, error: {}", target_entry_path, err))?;
+            match target_entry_file.read_to_string(&mut file_contents) {
+                Ok(_) => (),
+                // Binary files (ex. images in an `assets` directory) are copied as-is
+                Err(ref err) if err.kind() == io::ErrorKind::InvalidData => return Ok(()),
+                Err(err) => return Err(io_hint::error(format!("Failed to read newly created copy of unpacked data for: {:?}", target_entry_path), err)),
+            }
 
-            file_contents.replace("$PROJECT_NAME", &built_project_name)
+            file_contents
+                .replace("$BINDGEN_IMPORT", &bindgen_import)
+                .replace("$PROJECT_NAME", &js_global_name)
         };
 
         let mut target_entry_file = File::create(target_entry_path)
-            .map_err(|err| format_err!("Failed to re-create the newly created copy of unpacked data, error: {}", err))?;
+            .map_err(|err| io_hint::error("Failed to re-create the newly created copy of unpacked data".to_owned(), err))?;
         target_entry_file.write(new_file_contents.as_bytes())
-            .map_err(|err| format_err!("Failed to write the new file contents to the unpacked data copy, error: {}", err))?;
+            .map_err(|err| io_hint::error("Failed to write the new file contents to the unpacked data copy".to_owned(), err))?;
 
         Ok(())
     })?;
@@ -171,40 +420,340 @@ fn build_project_delegate(js_path_delegate : impl FnOnce() -> Result<(PathBuf, S
     if should_cleanup.0 {
         fs::remove_dir_all(js_path)?;
     }
+    timings.record("file copy", phase_start);
 
-    info!("Running wasm-bindgen, this may take some time.. ");
-    let wasm_output_path = format!("target/wasm32-unknown-unknown/debug/{}.wasm", built_project_name);
-    execute_command(
-        "wasm-bindgen",
-        &format!("{} --no-modules --no-modules-global {} --no-typescript --out-dir {}", wasm_output_path, built_project_name, target_dir),
-        &format!("Run wasm-bindgen, directing output to wasm-rgame `{}` folder", target_dir),
-    )?;
-    info!("done!\n");
+    let files_before_bindgen = bindgen_output::file_names_in_dir(target_dir_path)?;
 
-    let target_index_path = target_dir_path.join("index.html");
-    info!("Finished building project: {} successfully. View the deployed project at {:?}.\n", project_name, target_index_path.as_os_str());
+    let phase_start = Instant::now();
+    progress::run_step(config.progress, "Running wasm-bindgen, this may take some time", || {
+        execute_command_args(
+            &tool::wasm_bindgen(),
+            &bindgen_args,
+            &format!("Run wasm-bindgen, directing output to wasm-rgame `{}` folder", target_dir),
+        )
+    })?;
+    timings.record("wasm-bindgen", phase_start);
+
+    bindgen_output::check_emitted_files(target_dir_path, &files_before_bindgen, &config.index_name)?;
+
+    if config.package {
+        let archive_path = Path::new(OUTPUT_ROOT).join(format!("{}.tar.gz", output_dir_name));
+        tarball::package(target_dir_path, &archive_path, &output_dir_name)?;
+        info!("Packaged the project into {:?}.\n", archive_path);
+    }
+
+    if let Some(ref post_build) = config.post_build {
+        let phase_start = Instant::now();
+        info!("Running the post-build command `{}`..\n", post_build);
+        hook::run_post_build(post_build, target_dir_path, &project_name)?;
+        timings.record("post-build", phase_start);
+    }
+
+    if config.timings {
+        info!("Build timings: {}\n", timings.summary());
+    }
+
+    let target_index_path = target_dir_path.join(&config.index_name);
+    if !target_index_path.exists() {
+        wargo_warn!("The entry file `{}` does not exist in the output directory {:?}, \
+               check the `--index-name` option.\n", config.index_name, target_dir_path);
+    }
+
+    // Browsers refuse to fetch the wasm from a file:// page, the url is still printed
+    // absolute so it can be opened or served from anywhere
+    let target_index_path = target_index_path.canonicalize().unwrap_or(target_index_path);
+    info!(
+        "Finished building project: {} successfully in {}. View the deployed project at {} \
+         (serving the output directory with a local web server is recommended).\n",
+        project_name,
+        progress::format_duration(timings.total()),
+        file_url(&target_index_path),
+    );
+
+    let warning_count = warning_count();
+    if warning_count > 0 {
+        info!("Build completed with {} warning(s).\n", warning_count);
+
+        if config.deny_warnings {
+            return Err(WargoError::DeniedWarnings { count: warning_count }.into());
+        }
+    }
 
     Ok(())
 }
 
-fn for_each_file_in_dir(dir_path: &PathBuf, action: impl Fn(DirEntry, String) -> Result<()>) -> Result<()> {
-    for entry_path in fs::read_dir(dir_path)? {
-        if let Ok(entry_path) = entry_path {
-            let file_name = entry_path.file_name();
+/// Calls the action for each file in the directory and its subdirectories, with the
+/// path of the file relative to the directory. Hidden files and directories and the
+/// release's init templates (which aren't part of the bundle) are skipped.
+fn for_each_file_in_dir(dir_path: &Path, action: impl Fn(DirEntry, String) -> Result<()>) -> Result<()> {
+    for_each_file_in_subdir(dir_path, None, &action)
+}
+
+fn for_each_file_in_subdir<F: Fn(DirEntry, String) -> Result<()>>(dir_path: &Path, relative_dir: Option<&str>, action: &F) -> Result<()> {
+    // Sort the entries so files are processed in the same order on every platform
+    let mut entries = fs::read_dir(dir_path)?
+        .filter_map(|entry_path| entry_path.ok())
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|entry_path| entry_path.file_name());
+
+    for entry_path in entries {
+        let file_name = entry_path.file_name();
+
+        if let Ok(file_name) = file_name.clone().into_string() {
+            // ignore hidden files and directories (ex. .git)
+            if file_name.starts_with(".") {
+                continue;
+            }
+
+            let relative_path = match relative_dir {
+                Some(relative_dir) => format!("{}/{}", relative_dir, file_name),
+                None => file_name,
+            };
 
-            if let Ok(file_name) = file_name.clone().into_string() {
-                // ignore hidden files
-                if file_name.starts_with(".") {
+            if entry_path.path().is_dir() {
+                if relative_path == init::RELEASE_TEMPLATES_DIR {
+                    debug!("Skipping the release's init templates directory: {:?}\n", entry_path.path());
                     continue;
                 }
 
-                action(entry_path, file_name)?;
-            } else {
-                warn!("Failed to parse file_name into string: {:?}, skipping!", file_name);
+                for_each_file_in_subdir(&entry_path.path(), Some(&relative_path), action)?;
                 continue;
             }
+
+            action(entry_path, relative_path)?;
+        } else {
+            wargo_warn!("Failed to parse file_name into string: {:?}, skipping!", file_name);
+            continue;
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates the parent directories of a file copied from a subdirectory.
+fn create_parent_dir(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|err| io_hint::error(format!("Failed to create the directory {:?}", parent), err))?;
+    }
+
+    Ok(())
+}
+
+/// The output directory inside of the output root, erroring if the name isn't a
+/// plain directory name (ex. an `--example ../..`) that would escape the root.
+fn output_dir_path(output_root: &Path, output_dir_name: &str) -> Result<PathBuf> {
+    let mut components = Path::new(output_dir_name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(output_root.join(output_dir_name)),
+        _ => Err(format_err!("The output directory name `{}` must be a plain directory name inside {:?}!", output_dir_name, output_root)),
+    }
+}
+
+/// Removes everything in the output root (the output of every project, example and
+/// the releases cache) except for the build lock, for `--force-clean`.
+fn remove_output_root(output_root: &Path) -> Result<()> {
+    if !output_root.exists() {
+        return Ok(());
+    }
+
+    let entries = fs::read_dir(output_root)
+        .map_err(|err| io_hint::error(format!("Failed to read the {:?} directory", output_root), err))?;
+    for entry in entries {
+        let entry = entry
+            .map_err(|err| io_hint::error(format!("Failed to read the {:?} directory", output_root), err))?;
+        if entry.file_name() == lock::LOCK_FILE_NAME {
+            continue;
         }
+
+        let entry_path = entry.path();
+        let removed = if entry.file_type()?.is_dir() { fs::remove_dir_all(&entry_path) } else { fs::remove_file(&entry_path) };
+        removed.map_err(|err| io_hint::error(format!("Failed removing {:?}", entry_path), err))?;
     }
 
     Ok(())
 }
+
+/// Removes the previous output (including files since deleted from the js path)
+/// and creates the empty output directory.
+fn recreate_target_dir(target_dir_path: &Path) -> Result<()> {
+    if target_dir_path.exists() {
+        fs::remove_dir_all(target_dir_path)
+            .map_err(|err| io_hint::error("Failed removing existing wasm-rgame target directory".to_owned(), err))?;
+    }
+
+    fs::create_dir_all(target_dir_path)
+        .map_err(|err| io_hint::error("Failed creating wasm-rgame target directory".to_owned(), err))?;
+
+    Ok(())
+}
+
+/// The arguments of the `cargo build` of the project.
+fn cargo_build_args(build_target: &BuildTarget, profile: Option<&str>, locked: bool, frozen: bool) -> Vec<String> {
+    let mut cargo_args = vec!["build".to_owned(), "--target".to_owned(), "wasm32-unknown-unknown".to_owned()];
+    cargo_args.extend(build_target.cargo_args());
+    cargo_args.extend(profile::cargo_args(profile));
+
+    if locked {
+        cargo_args.push("--locked".to_owned());
+    }
+    if frozen {
+        cargo_args.push("--frozen".to_owned());
+    }
+
+    cargo_args
+}
+
+/// The file:// url of the absolute path, with the characters that would break the
+/// url (or where a terminal detects its end) percent-encoded.
+fn file_url(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    // Canonical paths on Windows are verbatim paths, ex. `\\?\C:\game`
+    let path = path.strip_prefix(r"\\?\").unwrap_or(&path).replace('\\', "/");
+
+    let mut url = if path.starts_with('/') { "file://".to_owned() } else { "file:///".to_owned() };
+    for c in path.chars() {
+        match c {
+            ' ' => url.push_str("%20"),
+            '%' => url.push_str("%25"),
+            '#' => url.push_str("%23"),
+            '?' => url.push_str("%3F"),
+            c => url.push(c),
+        }
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    #[test]
+    fn file_url_of_absolute_paths() {
+        assert_eq!(
+            file_url(Path::new("/home/me/my game/target/wasm-rgame/my-game/index.html")),
+            "file:///home/me/my%20game/target/wasm-rgame/my-game/index.html"
+        );
+        assert_eq!(
+            file_url(Path::new(r"\\?\C:\games\target\wasm-rgame\my-game\index.html")),
+            "file:///C:/games/target/wasm-rgame/my-game/index.html"
+        );
+    }
+
+    #[test]
+    fn for_each_file_in_dir_is_sorted_and_skips_hidden() {
+        let tmp_dir = TempDir::new().unwrap();
+        for file_name in &["c.js", "a.html", ".DS_Store", "b.css"] {
+            fs::write(tmp_dir.path().join(file_name), "").unwrap();
+        }
+
+        let file_names = RefCell::new(Vec::new());
+        for_each_file_in_dir(tmp_dir.path(), |_dir_entry, file_name| {
+            file_names.borrow_mut().push(file_name);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(file_names.into_inner(), vec!["a.html", "b.css", "c.js"]);
+    }
+
+    #[test]
+    fn for_each_file_in_dir_includes_subdirectories_except_templates() {
+        let tmp_dir = TempDir::new().unwrap();
+        for dir_name in &["assets/sprites", "templates", ".git"] {
+            fs::create_dir_all(tmp_dir.path().join(dir_name)).unwrap();
+        }
+        for file_name in &["index.html", "assets/sprites/ball.png", "assets/font.ttf", "templates/lib.rs", ".git/HEAD"] {
+            fs::write(tmp_dir.path().join(file_name), "").unwrap();
+        }
+
+        let file_names = RefCell::new(Vec::new());
+        for_each_file_in_dir(tmp_dir.path(), |_dir_entry, file_name| {
+            file_names.borrow_mut().push(file_name);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(file_names.into_inner(), vec!["assets/font.ttf", "assets/sprites/ball.png", "index.html"]);
+    }
+
+    #[test]
+    fn access_denied_statuses_do_not_fall_back_to_git() {
+        assert!(is_access_denied(Some(401)));
+        assert!(is_access_denied(Some(403)));
+        assert!(is_access_denied(Some(404)));
+        assert!(!is_access_denied(Some(502)));
+        assert!(!is_access_denied(None));
+    }
+
+    #[test]
+    fn recreate_target_dir_removes_orphaned_files() {
+        let tmp_dir = TempDir::new().unwrap();
+        let target_dir_path = tmp_dir.path().join("target/wasm-rgame/my-game");
+        fs::create_dir_all(target_dir_path.join("assets")).unwrap();
+        fs::write(target_dir_path.join("removed-from-js-path.js"), "").unwrap();
+        fs::write(target_dir_path.join("assets/sprite.png"), "").unwrap();
+
+        recreate_target_dir(&target_dir_path).unwrap();
+
+        assert!(target_dir_path.is_dir());
+        assert_eq!(fs::read_dir(&target_dir_path).unwrap().count(), 0);
+
+        // Also creates the directory for the first build
+        fs::remove_dir_all(tmp_dir.path().join("target")).unwrap();
+        recreate_target_dir(&target_dir_path).unwrap();
+        assert!(target_dir_path.is_dir());
+    }
+
+    #[test]
+    fn output_dir_path_stays_inside_output_root() {
+        let output_root = Path::new(OUTPUT_ROOT);
+        assert_eq!(output_dir_path(output_root, "my-game").unwrap(), PathBuf::from("target/wasm-rgame/my-game"));
+
+        for output_dir_name in &["", ".", "..", "../src", "examples/../..", "/tmp", "my-game/nested"] {
+            assert!(output_dir_path(output_root, output_dir_name).is_err(), "{:?} should be rejected", output_dir_name);
+        }
+    }
+
+    #[test]
+    fn remove_output_root_removes_every_project() {
+        let tmp_dir = TempDir::new().unwrap();
+        let output_root = tmp_dir.path().join("target/wasm-rgame");
+        fs::create_dir_all(output_root.join("old-name")).unwrap();
+        fs::create_dir_all(output_root.join("bouncing-balls")).unwrap();
+        fs::write(output_root.join(lock::LOCK_FILE_NAME), "").unwrap();
+        fs::write(tmp_dir.path().join("target/keep.txt"), "").unwrap();
+
+        remove_output_root(&output_root).unwrap();
+        let remaining = fs::read_dir(&output_root).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(remaining, vec![lock::LOCK_FILE_NAME]);
+        assert!(tmp_dir.path().join("target/keep.txt").exists());
+
+        fs::remove_dir_all(&output_root).unwrap();
+
+        // Nothing to remove on the first build
+        remove_output_root(&output_root).unwrap();
+    }
+
+    #[test]
+    fn cargo_build_args_forward_locked_and_frozen() {
+        assert_eq!(
+            cargo_build_args(&BuildTarget::Lib, None, false, false),
+            vec!["build", "--target", "wasm32-unknown-unknown", "--lib"]
+        );
+        assert_eq!(
+            cargo_build_args(&BuildTarget::Lib, Some("release"), true, false),
+            vec!["build", "--target", "wasm32-unknown-unknown", "--lib", "--release", "--locked"]
+        );
+        assert_eq!(
+            cargo_build_args(&BuildTarget::Lib, Some("release"), true, true),
+            vec!["build", "--target", "wasm32-unknown-unknown", "--lib", "--release", "--locked", "--frozen"]
+        );
+        assert_eq!(
+            cargo_build_args(&BuildTarget::Bin("level-editor".to_owned()), Some("release"), false, true),
+            vec!["build", "--target", "wasm32-unknown-unknown", "--bin", "level-editor", "--release", "--frozen"]
+        );
+    }
+}
@@ -0,0 +1,42 @@
+/// The cargo arguments that select the profile, `None` is cargo's default
+/// `dev` profile.
+pub fn cargo_args(profile: Option<&str>) -> Vec<String> {
+    match profile {
+        None | Some("dev") => Vec::new(),
+        Some("release") => vec!["--release".to_owned()],
+        Some(profile) => vec!["--profile".to_owned(), profile.to_owned()],
+    }
+}
+
+/// The directory under `target/wasm32-unknown-unknown` that cargo puts the
+/// output of the profile in, the built-in profiles have their own names.
+pub fn output_dir(profile: Option<&str>) -> &str {
+    match profile {
+        None | Some("dev") | Some("test") => "debug",
+        Some("bench") => "release",
+        Some(profile) => profile,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_profiles() {
+        assert!(cargo_args(None).is_empty());
+        assert_eq!(output_dir(None), "debug");
+
+        assert!(cargo_args(Some("dev")).is_empty());
+        assert_eq!(output_dir(Some("dev")), "debug");
+
+        assert_eq!(cargo_args(Some("release")), vec!["--release"]);
+        assert_eq!(output_dir(Some("release")), "release");
+    }
+
+    #[test]
+    fn custom_profile() {
+        assert_eq!(cargo_args(Some("release-wasm")), vec!["--profile", "release-wasm"]);
+        assert_eq!(output_dir(Some("release-wasm")), "release-wasm");
+    }
+}
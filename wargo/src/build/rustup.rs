@@ -0,0 +1,37 @@
+use super::*;
+
+const WASM_TARGET : &'static str = "wasm32-unknown-unknown";
+
+/// Checks that the wasm target is installed for the active toolchain, the
+/// install command can succeed while adding the target to another toolchain.
+pub fn check_wasm_target_installed() -> Result<()> {
+    let installed_targets = capture_command(
+        &tool::rustup(),
+        "target list --installed",
+        "List the installed targets of the toolchain"
+    )?;
+
+    if !has_wasm_target(&installed_targets) {
+        return Err(WargoError::WasmTargetNotInstalled.into());
+    }
+
+    Ok(())
+}
+
+/// Whether the output of `rustup target list --installed` contains the wasm target.
+fn has_wasm_target(installed_targets: &str) -> bool {
+    installed_targets.lines().any(|line| line.trim() == WASM_TARGET)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_wasm_target_in_installed_targets() {
+        assert!(has_wasm_target("x86_64-unknown-linux-gnu\nwasm32-unknown-unknown\n"));
+        assert!(!has_wasm_target("x86_64-unknown-linux-gnu\n"));
+        assert!(!has_wasm_target("wasm32-unknown-emscripten\n"));
+        assert!(!has_wasm_target(""));
+    }
+}
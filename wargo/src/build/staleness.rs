@@ -0,0 +1,78 @@
+use std::time::SystemTime;
+
+use super::*;
+
+/// Warns if any of the target's sources were modified after the wasm was built,
+/// which usually means an old build is being bundled (ex. with `--no-build`).
+/// This is only a hint, so a source that can't be read skips the check.
+pub fn check_wasm_is_fresh(wasm_path: &Path, source_paths: &[PathBuf]) {
+    let wasm_modified = match fs::metadata(wasm_path).and_then(|metadata| metadata.modified()) {
+        Ok(wasm_modified) => wasm_modified,
+        Err(err) => {
+            debug!("Skipping the staleness check, could not read the modified time of {:?}, error: {}\n", wasm_path, err);
+            return;
+        },
+    };
+
+    let mut newest_source = None;
+    for source_path in source_paths {
+        match newest_modified(source_path) {
+            Ok(modified) => newest_source = newest_source.max(modified),
+            Err(err) => {
+                debug!("Skipping the staleness check, could not read the modified time of {:?}, error: {}\n", source_path, err);
+                return;
+            },
+        }
+    }
+
+    if let Some((source_modified, source_path)) = newest_source {
+        if source_modified > wasm_modified {
+            wargo_warn!("{:?} was modified after the wasm {:?} was built, the bundled wasm may be stale!\n", source_path, wasm_path);
+        }
+    }
+}
+
+/// The most recently modified file at the path (searching directories recursively),
+/// None if the path doesn't exist.
+fn newest_modified(path: &Path) -> Result<Option<(SystemTime, PathBuf)>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let metadata = fs::metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(Some((metadata.modified()?, path.to_path_buf())));
+    }
+
+    let mut newest = None;
+    for entry in fs::read_dir(path)? {
+        newest = newest.max(newest_modified(&entry?.path())?);
+    }
+
+    Ok(newest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newest_modified_searches_directories() {
+        let tmp_dir = TempDir::new().unwrap();
+        let nested_dir = tmp_dir.path().join("src").join("levels");
+        fs::create_dir_all(&nested_dir).unwrap();
+        fs::write(tmp_dir.path().join("src").join("lib.rs"), "").unwrap();
+        fs::write(nested_dir.join("level_one.rs"), "").unwrap();
+
+        let (modified, path) = newest_modified(&tmp_dir.path().join("src")).unwrap().expect("sources exist");
+        let newest_file_modified = [tmp_dir.path().join("src").join("lib.rs"), nested_dir.join("level_one.rs")].iter()
+            .map(|path| fs::metadata(path).unwrap().modified().unwrap())
+            .max()
+            .unwrap();
+        assert_eq!(modified, newest_file_modified);
+        assert!(path.starts_with(tmp_dir.path()));
+
+        assert!(newest_modified(&tmp_dir.path().join("examples")).unwrap().is_none());
+        assert!(newest_modified(&nested_dir.join("empty")).unwrap().is_none());
+    }
+}
@@ -0,0 +1,76 @@
+use super::*;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+const GZIP_MAGIC_BYTES : [u8; 2] = [0x1f, 0x8b];
+
+/// Checks that the downloaded bytes look like a gzipped tarball before unpacking,
+/// so that a truncated download or an HTML error page gives an actionable error.
+pub fn validate(bytes: &[u8]) -> Result<()> {
+    if bytes.is_empty() {
+        return Err(format_err!("The downloaded file is not a valid tarball, it is empty!"));
+    }
+
+    if !bytes.starts_with(&GZIP_MAGIC_BYTES) {
+        return Err(format_err!("The downloaded file is not a valid tarball, it is not gzip compressed ({} bytes)!", bytes.len()));
+    }
+
+    Ok(())
+}
+
+/// Creates a gzipped tarball at `archive_path` containing the directory under
+/// the `root_name` directory, overwriting any existing archive.
+pub fn package(dir_path: &Path, archive_path: &Path, root_name: &str) -> Result<()> {
+    let archive_file = File::create(archive_path)
+        .map_err(|err| format_err!("Failed to create the archive at {:?}, error: {}", archive_path, err))?;
+
+    let mut builder = tar::Builder::new(GzEncoder::new(archive_file, Compression::default()));
+    builder.append_dir_all(root_name, dir_path)
+        .map_err(|err| format_err!("Failed to add {:?} to the archive, error: {}", dir_path, err))?;
+    builder.into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(|err| format_err!("Failed to finish writing the archive at {:?}, error: {}", archive_path, err))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_round_trips_directory() {
+        let tmp_dir = TempDir::new().unwrap();
+        let dir_path = tmp_dir.path().join("my-game");
+        fs::create_dir(&dir_path).unwrap();
+        fs::write(dir_path.join("index.html"), "<html></html>").unwrap();
+
+        let archive_path = tmp_dir.path().join("my-game.tar.gz");
+        // Existing archives are overwritten
+        fs::write(&archive_path, "stale").unwrap();
+        package(&dir_path, &archive_path, "my-game").unwrap();
+
+        let archive_bytes = fs::read(&archive_path).unwrap();
+        validate(&archive_bytes).unwrap();
+
+        let unpack_dir = TempDir::new().unwrap();
+        tar::Archive::new(GzDecoder::new(&archive_bytes[..])).unpack(unpack_dir.path()).unwrap();
+        assert_eq!(fs::read_to_string(unpack_dir.path().join("my-game/index.html")).unwrap(), "<html></html>");
+    }
+
+    #[test]
+    fn validate_rejects_empty_download() {
+        assert!(validate(&[]).is_err());
+    }
+
+    #[test]
+    fn validate_rejects_html_error_page() {
+        assert!(validate(b"<!DOCTYPE html><html>Not Found</html>").is_err());
+    }
+
+    #[test]
+    fn validate_accepts_gzip_stream() {
+        assert!(validate(&[0x1f, 0x8b, 0x08, 0x00]).is_ok());
+    }
+}
@@ -0,0 +1,46 @@
+use std::time::{Duration, Instant};
+
+use progress::format_duration;
+
+/// Records how long each phase of the build took.
+pub struct Timings {
+    start: Instant,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    pub fn new() -> Timings {
+        Timings { start: Instant::now(), phases: Vec::new() }
+    }
+
+    /// Records the phase as having run from `phase_start` until now.
+    pub fn record(&mut self, phase: &'static str, phase_start: Instant) {
+        self.phases.push((phase, phase_start.elapsed()));
+    }
+
+    pub fn total(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Summary of all the recorded phases, ex: "cargo build: 48.2s, wasm-bindgen: 3.1s".
+    pub fn summary(&self) -> String {
+        self.phases.iter()
+            .map(|(phase, duration)| format!("{}: {}", phase, format_duration(*duration)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_lists_phases_in_order() {
+        let mut timings = Timings::new();
+        timings.phases.push(("cargo build", Duration::from_millis(48_200)));
+        timings.phases.push(("wasm-bindgen", Duration::from_millis(3_100)));
+
+        assert_eq!(timings.summary(), "cargo build: 48.2s, wasm-bindgen: 3.1s");
+    }
+}
@@ -0,0 +1,95 @@
+//! Optional `wargo.toml` in the project root, used to set persistent defaults
+//! for the build options.
+//!
+//! Precedence: command-line flags > wargo.toml > built-in defaults.
+//!
+//! ```toml
+//! [build]
+//! js-path = "../wasm-rgame-js"
+//! timings = true
+//! ```
+use super::*;
+
+const WARGO_CONFIG_PATH : &'static str = "wargo.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WargoConfig {
+    #[serde(default)]
+    pub build: BuildConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BuildConfig {
+    pub js_path: Option<PathBuf>,
+    pub js_version: Option<String>,
+    pub progress: Option<ProgressMode>,
+    pub timings: Option<bool>,
+    pub keep_temp: Option<bool>,
+    pub retries: Option<u32>,
+    pub prefer_source: Option<bool>,
+    pub strip_components: Option<usize>,
+    pub use_git: Option<bool>,
+    pub bindgen_target: Option<BindgenTarget>,
+    pub typescript: Option<bool>,
+    pub js_repo: Option<String>,
+    pub github_host: Option<String>,
+    pub profile: Option<String>,
+    pub deny_warnings: Option<bool>,
+    pub index_name: Option<String>,
+    pub post_build: Option<String>,
+}
+
+impl WargoConfig {
+    /// Loads the wargo.toml in the current directory, or the default
+    /// (empty) config if there is none.
+    pub fn load() -> Result<WargoConfig> {
+        if !Path::new(WARGO_CONFIG_PATH).exists() {
+            return Ok(WargoConfig::default());
+        }
+
+        let contents = fs::read_to_string(WARGO_CONFIG_PATH)
+            .map_err(|err| format_err!("Cannot read {}, error: {}", WARGO_CONFIG_PATH, err))?;
+
+        WargoConfig::from_str(&contents)
+    }
+
+    fn from_str(contents: &str) -> Result<WargoConfig> {
+        toml::from_str(contents)
+            .map_err(|err| format_err!("Cannot parse {}, error: {}", WARGO_CONFIG_PATH, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_build_section() {
+        let config = WargoConfig::from_str(r#"
+            [build]
+            js-path = "../wasm-rgame-js"
+            progress = "never"
+            timings = true
+            retries = 5
+        "#).unwrap();
+
+        assert_eq!(config.build.js_path, Some(PathBuf::from("../wasm-rgame-js")));
+        assert_eq!(config.build.progress, Some(ProgressMode::Never));
+        assert_eq!(config.build.timings, Some(true));
+        assert_eq!(config.build.keep_temp, None);
+        assert_eq!(config.build.retries, Some(5));
+    }
+
+    #[test]
+    fn parses_empty_config() {
+        let config = WargoConfig::from_str("").unwrap();
+        assert!(config.build.js_path.is_none());
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        assert!(WargoConfig::from_str("[build]\njs-pth = \"typo\"\n").is_err());
+    }
+}
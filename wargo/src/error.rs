@@ -0,0 +1,82 @@
+use std::io;
+use std::path::PathBuf;
+
+use semver::Version;
+use toml;
+
+/// The kinds of failures wargo can run into, so that callers (and tests) can
+/// match on them by downcasting the `failure::Error`.
+#[derive(Debug, Fail)]
+pub enum WargoError {
+    #[fail(display = "Cannot find Cargo.toml in project directory, error: {}", error)]
+    ManifestNotFound { #[cause] error: io::Error },
+
+    #[fail(display = "Cannot find Cargo.toml in {:?} or any of its parent directories!", dir)]
+    ManifestNotFoundInAncestors { dir: PathBuf },
+
+    #[fail(display = "The manifest path {:?} is not a Cargo.toml file!", path)]
+    ManifestPathInvalid { path: PathBuf },
+
+    #[fail(display = "Cannot parse Cargo.toml, error: {}", error)]
+    ManifestParse { #[cause] error: toml::de::Error },
+
+    #[fail(display = "The Cargo.toml in this directory is a workspace (virtual manifest), \
+                      run wargo inside of a member crate of the workspace instead!")]
+    VirtualManifest,
+
+    #[fail(display = "The project must be built as a `cdylib` for wasm-bindgen to work!\n\
+                      Add the following to your Cargo.toml:\n\n\
+                      [lib]\n\
+                      crate-type = [\"cdylib\"]\n")]
+    NotCdylib,
+
+    #[fail(display = "Cannot find / read Cargo.lock in project directory, error: {}", error)]
+    LockNotFound { #[cause] error: io::Error },
+
+    #[fail(display = "Cannot find wasm-rgame package in the Cargo.lock file!")]
+    LockParse,
+
+    #[fail(display = "Found no releases for {}!", repo)]
+    NoReleases { repo: String },
+
+    #[fail(display = "Found no release of wasm-rgame-js with tag `{}`!", tag)]
+    ReleaseTagNotFound { tag: String },
+
+    #[fail(display = "Found no valid releases for wasm-rgame version `{}`!", wasm_rgame_version)]
+    NoCompatibleRelease { wasm_rgame_version: Version },
+
+    /// The status is set when the server responded with an error status.
+    #[fail(display = "{}", message)]
+    NetworkError { message: String, status: Option<u16> },
+
+    #[fail(display = "Could not find the built wasm file at the expected path: `{}`!\n\
+                      Make sure that the Cargo.toml has `crate-type = [\"cdylib\"]` under the `[lib]` section \
+                      and that the project was built to the `{}` profile directory.", path, profile_dir)]
+    WasmNotFound { path: String, profile_dir: String },
+
+    #[fail(display = "Could not find the built wasm file at the expected path: `{}`!\n\
+                      Build the project with `cargo build --target wasm32-unknown-unknown` before using `--no-build`.", path)]
+    WasmNotBuilt { path: String },
+
+    #[fail(display = "Failed to execute, context: `{}`, error: {}\nFull command: `{}`", context, error, command)]
+    CommandNotExecuted { command: String, context: String, #[cause] error: io::Error },
+
+    #[fail(display = "Command failed, context: `{}`\n\n\nStdout:\n{}\n\n\nStderr:\n{}\n\n\nFull command: `{}`", context, stdout, stderr, command)]
+    CommandFailed { command: String, context: String, stdout: String, stderr: String },
+
+    #[fail(display = "The wasm32-unknown-unknown target is not installed for the project's toolchain!\n\
+                      Check the active toolchain with `rustup show` and install the target with \
+                      `rustup target add wasm32-unknown-unknown --toolchain nightly`.")]
+    WasmTargetNotInstalled,
+
+    #[fail(display = "The post-build command `{}` failed with {}!", command, status)]
+    PostBuildFailed { command: String, status: String },
+
+    #[fail(display = "The build emitted {} warning(s) and `--deny-warnings` is set!", count)]
+    DeniedWarnings { count: usize },
+
+    #[fail(display = "Failed to initialize project with `cargo init`, does the project already exist?\n\
+                      You can reference the lib.rs file of `wrg-snake` to manually add the entrypoint:\n\
+                      https://github.com/DarrenTsung/wrg-snake/blob/master/src/lib.rs")]
+    ProjectAlreadyExists,
+}
@@ -0,0 +1,188 @@
+use super::*;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// How the diagnostics are printed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InfoFormat {
+    /// Readable lines, logged like the rest of wargo's output.
+    Human,
+    /// A single JSON object on stdout, for CI systems to consume.
+    Json,
+}
+
+impl FromStr for InfoFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<InfoFormat, String> {
+        match s {
+            "human" => Ok(InfoFormat::Human),
+            "json" => Ok(InfoFormat::Json),
+            _ => Err(format!("Invalid format: `{}`, expected one of: human, json", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    /// The check doesn't apply, ex. the project checks outside of a project directory.
+    Skip,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            CheckStatus::Pass => "pass",
+            CheckStatus::Fail => "fail",
+            CheckStatus::Skip => "skip",
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+/// The versions of wargo and the tools it uses, along with the checks of
+/// whether the environment can build a project.
+#[derive(Debug, Serialize)]
+pub struct Diagnostics {
+    /// Serialized as a JSON object, keeping the order of the versions.
+    #[serde(serialize_with = "serialize_versions")]
+    pub versions: Vec<(&'static str, String)>,
+    pub checks: Vec<Check>,
+}
+
+impl Diagnostics {
+    pub fn collect() -> Diagnostics {
+        let mut checks = Vec::new();
+
+        let wasm_rgame_version = if Path::new("Cargo.toml").exists() {
+            match wasm_rgame_version() {
+                Ok(version) => {
+                    checks.push(Check { name: "project", status: CheckStatus::Pass, detail: format!("using wasm-rgame {}", version) });
+                    version.to_string()
+                },
+                Err(err) => {
+                    checks.push(Check { name: "project", status: CheckStatus::Fail, detail: err.to_string() });
+                    format!("unknown ({})", err)
+                },
+            }
+        } else {
+            checks.push(Check { name: "project", status: CheckStatus::Skip, detail: "not in a project directory".to_owned() });
+            "not in a project directory".to_owned()
+        };
+
+        let wasm_bindgen_version = probe_version(&tool::wasm_bindgen(), &["--version"]);
+        checks.push(version_check("wasm-bindgen", &wasm_bindgen_version));
+        let rustc_version = probe_version("rustc", &["--version"]);
+        checks.push(version_check("rustc", &rustc_version));
+        let toolchain_version = probe_version(&tool::rustup(), &["show", "active-toolchain"]);
+        checks.push(version_check("rustup", &toolchain_version));
+
+        checks.push(match build::check_wasm_target_installed() {
+            Ok(()) => Check { name: "wasm target", status: CheckStatus::Pass, detail: "wasm32-unknown-unknown is installed".to_owned() },
+            Err(err) => Check { name: "wasm target", status: CheckStatus::Fail, detail: err.to_string() },
+        });
+
+        Diagnostics {
+            versions: vec![
+                ("wargo", env!("CARGO_PKG_VERSION").to_owned()),
+                ("wasm-rgame", wasm_rgame_version),
+                ("wasm-bindgen", wasm_bindgen_version.unwrap_or_else(|err| err)),
+                ("rustc", rustc_version.unwrap_or_else(|err| err)),
+                ("rust toolchain", toolchain_version.unwrap_or_else(|err| err)),
+            ],
+            checks,
+        }
+    }
+
+    pub fn failed_checks(&self) -> usize {
+        self.checks.iter().filter(|check| check.status == CheckStatus::Fail).count()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+fn serialize_versions<S: serde::Serializer>(versions: &[(&'static str, String)], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_map(versions.iter().map(|&(name, ref version)| (name, version)))
+}
+
+/// Prints the versions of wargo and the tools it uses, to help with bug reports,
+/// erroring if any of the checks failed.
+pub fn print_info(format: InfoFormat) -> Result<()> {
+    let diagnostics = Diagnostics::collect();
+
+    match format {
+        InfoFormat::Human => {
+            for &(name, ref version) in &diagnostics.versions {
+                info!("{}: {}\n", name, version);
+            }
+            info!("\n");
+            for check in &diagnostics.checks {
+                info!("[{}] {}: {}\n", check.status, check.name, check.detail);
+            }
+        },
+        InfoFormat::Json => println!("{}", diagnostics.to_json()?),
+    }
+
+    let failed_checks = diagnostics.failed_checks();
+    if failed_checks > 0 {
+        return Err(format_err!("{} of the diagnostic checks failed!", failed_checks));
+    }
+
+    Ok(())
+}
+
+fn version_check(name: &'static str, version: &std::result::Result<String, String>) -> Check {
+    match *version {
+        Ok(ref version) => Check { name, status: CheckStatus::Pass, detail: version.clone() },
+        Err(ref err) => Check { name, status: CheckStatus::Fail, detail: err.clone() },
+    }
+}
+
+/// Runs the command and returns the first line of its output, or a
+/// description of why the version couldn't be found.
+fn probe_version(command: &str, args: &[&str]) -> std::result::Result<String, String> {
+    match Command::new(command).args(args).output() {
+        Ok(ref output) if output.status.success() => {
+            Ok(String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or("").trim().to_owned())
+        },
+        Ok(_) => Err(format!("unknown (`{} {}` failed)", command, args.join(" "))),
+        Err(_) => Err("not installed".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnostics_to_json() {
+        let diagnostics = Diagnostics {
+            versions: vec![("wargo", "0.1.0".to_owned())],
+            checks: vec![
+                Check { name: "rustc", status: CheckStatus::Pass, detail: "rustc 1.30.0".to_owned() },
+                Check { name: "project", status: CheckStatus::Skip, detail: "not in a project directory".to_owned() },
+            ],
+        };
+
+        let json : serde_json::Value = serde_json::from_str(&diagnostics.to_json().unwrap()).unwrap();
+        assert_eq!(json["versions"]["wargo"], "0.1.0");
+        assert_eq!(json["checks"][0]["name"], "rustc");
+        assert_eq!(json["checks"][0]["status"], "pass");
+        assert_eq!(json["checks"][0]["detail"], "rustc 1.30.0");
+        assert_eq!(json["checks"][1]["status"], "skip");
+        assert_eq!(json["checks"][1]["detail"], "not in a project directory");
+        assert_eq!(diagnostics.failed_checks(), 0);
+    }
+}
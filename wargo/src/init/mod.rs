@@ -1,13 +1,128 @@
 use super::*;
 
-use std::fs::OpenOptions;
-
 const LIB_RS_TEMPLATE_TEXT : &'static str = include_str!("lib.rs.template");
 const BOOTSTRAP_RS_TEMPLATE_TEXT : &'static str = include_str!("bootstrap.rs.template");
 const SIMPLE_BOX_RS_TEMPLATE_TEXT : &'static str = include_str!("simple_box.rs.template");
 const CARGO_TOML_APPEND_TEXT : &'static str = include_str!("cargo_toml.append");
 
-pub fn initialize_entrypoint(name: Option<String>) -> Result<()> {
+/// The wasm-rgame version requirement added to the Cargo.toml when the latest
+/// version can't be found.
+const DEFAULT_WASM_RGAME_VERSION : &'static str = "*";
+
+/// The directory in a wasm-rgame-js release that contains the init templates,
+/// with the same file names as the built-in templates.
+pub const RELEASE_TEMPLATES_DIR : &'static str = "templates";
+
+/// The files used to initialize a project.
+pub struct Templates {
+    lib_rs: String,
+    bootstrap_rs: String,
+    simple_box_rs: String,
+    cargo_toml_append: String,
+}
+
+impl Templates {
+    /// The templates built into wargo.
+    pub fn embedded() -> Templates {
+        Templates {
+            lib_rs: LIB_RS_TEMPLATE_TEXT.to_owned(),
+            bootstrap_rs: BOOTSTRAP_RS_TEMPLATE_TEXT.to_owned(),
+            simple_box_rs: SIMPLE_BOX_RS_TEMPLATE_TEXT.to_owned(),
+            cargo_toml_append: CARGO_TOML_APPEND_TEXT.to_owned(),
+        }
+    }
+
+    /// The templates from the latest wasm-rgame-js release, so they match the js
+    /// the project will be built with. Falls back to the built-in templates if
+    /// they can't be fetched (ex. when offline).
+    pub fn from_latest_release(repo: &ReleaseRepo) -> Templates {
+        info!("Fetching the templates from the latest wasm-rgame-js release.. ");
+        match Templates::download(repo) {
+            Ok(templates) => {
+                info!("done!\n");
+                templates
+            },
+            Err(err) => {
+                wargo_warn!("failed!\nCould not fetch the templates from the wasm-rgame-js release, \
+                       using the built-in templates instead. Error: {}\n", err);
+                Templates::embedded()
+            },
+        }
+    }
+
+    fn download(repo: &ReleaseRepo) -> Result<Templates> {
+        let release = release::fetch_latest_release(repo)?;
+        let tarball_bytes = build::download_release_tarball(&release, repo, false)?;
+        let (_unpack_tmp_dir, unpacked_dir_path) = build::unpack_release(&tarball_bytes, None)?;
+
+        Templates::from_dir(&unpacked_dir_path.join(RELEASE_TEMPLATES_DIR))
+    }
+
+    fn from_dir(templates_dir: &Path) -> Result<Templates> {
+        let read_template = |file_name: &str| {
+            fs::read_to_string(templates_dir.join(file_name))
+                .map_err(|err| format_err!("Failed to read the template {:?}, error: {}", templates_dir.join(file_name), err))
+        };
+
+        Ok(Templates {
+            lib_rs: read_template("lib.rs.template")?,
+            bootstrap_rs: read_template("bootstrap.rs.template")?,
+            simple_box_rs: read_template("simple_box.rs.template")?,
+            cargo_toml_append: read_template("cargo_toml.append")?,
+        })
+    }
+
+    /// Fills in the `$WASM_RGAME_VERSION` of the dependencies added to the Cargo.toml.
+    pub fn set_wasm_rgame_version(&mut self, wasm_rgame_version: &str) {
+        self.cargo_toml_append = self.cargo_toml_append.replace("$WASM_RGAME_VERSION", wasm_rgame_version);
+    }
+
+    /// The bootstrap files for the project, written after `cargo init`.
+    fn files(&self, built_project_name: &str) -> Vec<TemplateFile> {
+        vec![
+            TemplateFile { path: "src/lib.rs", contents: self.lib_rs.replace("$PROJECT_NAME", built_project_name) },
+            TemplateFile { path: "src/bootstrap.rs", contents: self.bootstrap_rs.clone() },
+            TemplateFile { path: "src/simple_box.rs", contents: self.simple_box_rs.clone() },
+        ]
+    }
+}
+
+/// Shown as the wasm-rgame version of a dry run, which doesn't look up the latest release.
+const LATEST_VERSION_PLACEHOLDER : &'static str = "<latest wasm-rgame version>";
+
+/// The templates for a dry run, which doesn't fetch (or cache) anything: the built-in
+/// templates, with a placeholder for the wasm-rgame version unless it's pinned.
+pub fn dry_run_templates(templates_from_release: bool, pinned_version: Option<String>) -> Templates {
+    if templates_from_release {
+        info!("The templates would be fetched from the latest wasm-rgame-js release, showing the built-in templates instead.\n");
+    }
+
+    let mut templates = Templates::embedded();
+    templates.set_wasm_rgame_version(&pinned_version.unwrap_or_else(|| LATEST_VERSION_PLACEHOLDER.to_owned()));
+    templates
+}
+
+/// The wasm-rgame version for a new project, the version of the latest wasm-rgame-js
+/// release (which is released alongside wasm-rgame) unless the version is pinned.
+pub fn wasm_rgame_version(pinned_version: Option<String>, repo: &ReleaseRepo) -> String {
+    if let Some(pinned_version) = pinned_version {
+        return pinned_version;
+    }
+
+    let latest_version = release::fetch_latest_release(repo)
+        .ok()
+        .and_then(|release| release::release_version(&release));
+    match latest_version {
+        Some(latest_version) => latest_version.to_string(),
+        None => {
+            wargo_warn!("Could not find the latest wasm-rgame version, adding wasm-rgame = \"{}\" instead. \
+                   Use `--wasm-rgame-version` to choose the version.\n", DEFAULT_WASM_RGAME_VERSION);
+            DEFAULT_WASM_RGAME_VERSION.to_owned()
+        },
+    }
+}
+
+pub fn initialize_entrypoint(name: Option<String>, templates: &Templates) -> Result<()> {
     info!("Initializing the project.. ");
     let name_arg = if let Some(name) = name {
         format!("--name {}", name)
@@ -16,56 +131,340 @@ pub fn initialize_entrypoint(name: Option<String>) -> Result<()> {
     };
 
     if let Err(_err) = execute_command(
-        "cargo",
+        &tool::cargo(),
         &format!("init --lib {}", name_arg),
         "Initialize project with `cargo init --lib`"
     ) {
-        return Err(format_err!("Failed to initialize project with `cargo init`, does the project already exist?\n\
-                                You can reference the lib.rs file of `wrg-snake` to manually add the entrypoint:\n\
-                                https://github.com/DarrenTsung/wrg-snake/blob/master/src/lib.rs"));
+        return Err(WargoError::ProjectAlreadyExists.into());
     }
     info!("done!\n");
 
     let project_name = project_name()?;
-    let built_project_name = built_project_name(&project_name);
+    let built_project_name = built_project_name()?;
 
     info!("Adding in bootstrap files.. ");
-    {
-        let mut lib_rs = File::create("src/lib.rs")
-            .map_err(|err| format_err!("Failed to open src/lib.rs with `File::create()`, error: {}", err))?;
-        let lib_rs_text = LIB_RS_TEMPLATE_TEXT.replace("$PROJECT_NAME", &built_project_name);
+    for template_file in templates.files(&built_project_name) {
+        let mut file = File::create(template_file.path)
+            .map_err(|err| format_err!("Failed to open {} with `File::create()`, error: {}", template_file.path, err))?;
 
-        lib_rs.write(lib_rs_text.as_bytes())
-            .map_err(|err| format_err!("Failed to write template into src/lib.rs, error: {}", err))?;
+        file.write(template_file.contents.as_bytes())
+            .map_err(|err| format_err!("Failed to write template into {}, error: {}", template_file.path, err))?;
     }
 
     {
-        let mut cargo_toml = OpenOptions::new()
-            .append(true)
-            .open("Cargo.toml")
-            .map_err(|err| format_err!("Failed to open Cargo.toml to add dependencies, error: {}", err))?;
+        let cargo_toml_contents = fs::read_to_string("Cargo.toml")
+            .map_err(|err| format_err!("Failed to read Cargo.toml to add the dependencies, error: {}", err))?;
 
-        cargo_toml.write(CARGO_TOML_APPEND_TEXT.as_bytes())
-            .map_err(|err| format_err!("Failed to write dependencies into Cargo.toml, error: {}", err))?;
+        let cargo_toml_contents = merge_dependencies(&cargo_toml_contents, &templates.cargo_toml_append)?;
+        let cargo_toml_contents = ensure_lib_crate_type(&cargo_toml_contents)?.unwrap_or(cargo_toml_contents);
+        fs::write("Cargo.toml", cargo_toml_contents)
+            .map_err(|err| format_err!("Failed to write the dependencies and crate-type into Cargo.toml, error: {}", err))?;
     }
 
-    {
-        let mut bootstrap_rs = File::create("src/bootstrap.rs")
-            .map_err(|err| format_err!("Failed to open src/bootstrap.rs with `File::create()`, error: {}", err))?;
+    check_cdylib_crate_type()?;
+    info!("done!\n");
+
+    info!("Finished initializing project: {} successfully. Run `wargo build` next to get started!\n", project_name);
+    Ok(())
+}
 
-        bootstrap_rs.write(BOOTSTRAP_RS_TEMPLATE_TEXT.as_bytes())
-            .map_err(|err| format_err!("Failed to write template into src/bootstrap.rs, error: {}", err))?;
+/// Prints what initializing the project in the directory would do, without
+/// touching the filesystem.
+pub fn print_plan(name: Option<String>, project_dir: &Path, templates: &Templates) -> Result<()> {
+    let project_name = match name {
+        Some(name) => name,
+        None => project_dir.file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .ok_or_else(|| format_err!("Could not find the project name from the path: {:?}, pass it with `--name`", project_dir))?,
+    };
+
+    if project_dir.join("Cargo.toml").exists() {
+        wargo_warn!("{:?} already contains a Cargo.toml, initializing the project would fail!\n", project_dir);
     }
 
-    {
-        let mut simple_box_rs = File::create("src/simple_box.rs")
-            .map_err(|err| format_err!("Failed to open src/simple_box.rs with `File::create()`, error: {}", err))?;
+    info!("Would initialize the project `{}` in {:?}:\n", project_name, project_dir);
+    info!("  run `cargo init --lib`, creating the Cargo.toml\n");
+    for template_file in templates.files(&::cargo_toml::crate_name(&project_name)) {
+        let action = if project_dir.join(template_file.path).exists() { "overwrite" } else { "create" };
+        info!("  {} {}\n", action, template_file.path);
+    }
 
-        simple_box_rs.write(SIMPLE_BOX_RS_TEMPLATE_TEXT.as_bytes())
-            .map_err(|err| format_err!("Failed to write template into src/simple_box.rs, error: {}", err))?;
+    info!("  append to the Cargo.toml [dependencies]:\n");
+    for line in templates.cargo_toml_append.lines() {
+        info!("    {}\n", line);
     }
-    info!("done!\n");
+    info!("  set `crate-type = [\"cdylib\", \"rlib\"]` in the Cargo.toml [lib] section\n");
 
-    info!("Finished initializing project: {} successfully. Run `wargo build` next to get started!\n", project_name);
     Ok(())
 }
+
+/// A bootstrap file written into the project by `wargo init`.
+struct TemplateFile {
+    path: &'static str,
+    contents: String,
+}
+
+/// Adds the dependencies (the `name = version` lines of the cargo_toml.append template)
+/// to the `[dependencies]` table, creating the table if needed. Dependencies that
+/// are already in the table are left as they are. The lines are inserted as text so
+/// the comments, formatting and order of the rest of the Cargo.toml are kept.
+fn merge_dependencies(cargo_toml_contents: &str, dependencies_toml: &str) -> Result<String> {
+    let cargo_toml = cargo_toml_contents.parse::<toml::Value>()
+        .map_err(|err| format_err!("Failed to parse Cargo.toml, error: {}", err))?;
+    let existing_dependencies = cargo_toml.get("dependencies").and_then(|dependencies| dependencies.as_table());
+
+    let mut new_lines = Vec::new();
+    for line in dependency_lines(dependencies_toml)? {
+        let name = line.parse::<toml::Value>().ok()
+            .and_then(|value| value.as_table().and_then(|table| table.keys().next().cloned()))
+            .ok_or_else(|| format_err!("Failed to parse the dependency `{}` in the dependencies template!", line))?;
+        if existing_dependencies.map(|dependencies| dependencies.contains_key(&name)).unwrap_or(false) {
+            continue;
+        }
+
+        new_lines.push(line);
+    }
+
+    let mut lines = cargo_toml_contents.lines().collect::<Vec<_>>();
+    match lines.iter().position(|line| is_table_header(line, "dependencies")) {
+        Some(header_index) => {
+            // After the last dependency, before any blank lines / comments leading into the next table
+            let mut insert_index = lines.iter().skip(header_index + 1)
+                .position(|line| line.trim_start().starts_with('['))
+                .map(|index| header_index + 1 + index)
+                .unwrap_or_else(|| lines.len());
+            while insert_index > header_index + 1 {
+                let previous_line = lines[insert_index - 1].trim();
+                if !previous_line.is_empty() && !previous_line.starts_with('#') {
+                    break;
+                }
+                insert_index -= 1;
+            }
+
+            for (offset, line) in new_lines.iter().enumerate() {
+                lines.insert(insert_index + offset, line);
+            }
+        },
+        None => {
+            if lines.last().map(|line| !line.trim().is_empty()).unwrap_or(false) {
+                lines.push("");
+            }
+            lines.push("[dependencies]");
+            lines.extend(new_lines.iter().map(|line| line.as_str()));
+        },
+    }
+
+    let mut new_contents = lines.join("\n");
+    new_contents.push('\n');
+    Ok(new_contents)
+}
+
+/// The `name = version` / `name = { .. }` lines of the dependencies template, erroring
+/// on anything that isn't a dependency (ex. a `[lib]` table from an old template).
+fn dependency_lines(dependencies_toml: &str) -> Result<Vec<String>> {
+    let mut dependency_lines = Vec::new();
+    for line in dependencies_toml.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let value = line.parse::<toml::Value>()
+            .map_err(|err| format_err!("Failed to parse the line `{}` of the dependencies template, error: {}", line, err))?;
+        let is_dependency = value.as_table()
+            .filter(|table| table.len() == 1)
+            .and_then(|table| table.values().next())
+            .map(|value| value.is_str() || value.is_table())
+            .unwrap_or(false);
+        if !is_dependency {
+            return Err(format_err!("The line `{}` of the dependencies template is not a dependency, \
+                                    expected `name = \"version\"` or `name = {{ .. }}`!", line));
+        }
+
+        dependency_lines.push(line.to_owned());
+    }
+
+    Ok(dependency_lines)
+}
+
+/// Returns true if the line is the `[name]` header of the table.
+fn is_table_header(line: &str, name: &str) -> bool {
+    let line = line.split('#').next().unwrap_or("").trim();
+    line.starts_with('[') && line.ends_with(']') && !line.starts_with("[[") && line[1..line.len() - 1].trim() == name
+}
+
+/// Makes sure the `[lib]` section exists with a crate-type containing "cdylib",
+/// returning the new Cargo.toml contents if it had to be changed.
+fn ensure_lib_crate_type(cargo_toml_contents: &str) -> Result<Option<String>> {
+    let mut cargo_toml = cargo_toml_contents.parse::<toml::Value>()
+        .map_err(|err| format_err!("Failed to parse Cargo.toml, error: {}", err))?;
+
+    {
+        let root = cargo_toml.as_table_mut()
+            .ok_or_else(|| format_err!("Cargo.toml is not a table!"))?;
+
+        let lib = root.entry("lib".to_owned())
+            .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+        let lib = lib.as_table_mut()
+            .ok_or_else(|| format_err!("The `lib` entry in Cargo.toml is not a table!"))?;
+
+        let has_cdylib = lib.get("crate-type")
+            .and_then(|crate_type| crate_type.as_array())
+            .map(|crate_types| crate_types.iter().any(|c| c.as_str() == Some("cdylib")))
+            .unwrap_or(false);
+        if has_cdylib {
+            return Ok(None);
+        }
+
+        lib.insert("crate-type".to_owned(), toml::Value::Array(vec![
+            toml::Value::String("cdylib".to_owned()),
+            toml::Value::String("rlib".to_owned()),
+        ]));
+    }
+
+    let new_cargo_toml_contents = toml::to_string(&cargo_toml)
+        .map_err(|err| format_err!("Failed to serialize Cargo.toml, error: {}", err))?;
+
+    Ok(Some(new_cargo_toml_contents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARGO_INIT_TOML : &'static str = "[package]\n\
+                                            name = \"my-game\"\n\
+                                            version = \"0.1.0\"\n\
+                                            \n\
+                                            [dependencies]\n";
+
+    #[test]
+    fn ensure_lib_crate_type_inserts_lib_section() {
+        let mut templates = Templates::embedded();
+        templates.set_wasm_rgame_version(DEFAULT_WASM_RGAME_VERSION);
+        let contents = format!("{}{}", CARGO_INIT_TOML, templates.cargo_toml_append);
+        let new_contents = ensure_lib_crate_type(&contents).unwrap().expect("lib section is added");
+
+        let cargo_toml = CargoToml::from_str(&new_contents).unwrap();
+        assert_eq!(cargo_toml.package.name, "my-game");
+        assert_eq!(cargo_toml.lib.unwrap().crate_type, Some(vec!["cdylib".to_owned(), "rlib".to_owned()]));
+
+        let value = new_contents.parse::<toml::Value>().unwrap();
+        assert_eq!(value["dependencies"]["wasm-rgame"].as_str(), Some("*"));
+    }
+
+    #[test]
+    fn merge_dependencies_into_existing_table() {
+        let contents = format!("{}serde = \"1.0\"\n\n[profile.release]\nlto = true\n", CARGO_INIT_TOML);
+        let new_contents = merge_dependencies(&contents, "wasm-rgame = \"0.3.1\"\nwasm-bindgen = \"*\"\nserde = \"*\"\n").unwrap();
+
+        assert_eq!(new_contents.matches("[dependencies]").count(), 1);
+        let value = new_contents.parse::<toml::Value>().unwrap();
+        let dependency_names = value["dependencies"].as_table().unwrap().keys().cloned().collect::<Vec<_>>();
+        assert_eq!(dependency_names, vec!["serde", "wasm-bindgen", "wasm-rgame"]);
+        assert_eq!(value["dependencies"]["serde"].as_str(), Some("1.0"));
+        assert_eq!(value["dependencies"]["wasm-rgame"].as_str(), Some("0.3.1"));
+        assert_eq!(value["profile"]["release"]["lto"].as_bool(), Some(true));
+        assert_eq!(CargoToml::from_str(&new_contents).unwrap().package.name, "my-game");
+    }
+
+    #[test]
+    fn merge_dependencies_keeps_comments_and_section_order() {
+        let contents = "[package]\n\
+                        name = \"my-game\"\n\
+                        # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html\n\
+                        \n\
+                        [dependencies]  # the game's dependencies\n\
+                        serde = \"1.0\" # for the save files\n\
+                        \n\
+                        # Faster release builds\n\
+                        [profile.release]\n\
+                        lto = true\n";
+        let new_contents = merge_dependencies(contents, "wasm-rgame = \"0.3.1\"\nserde = \"*\"\n").unwrap();
+
+        assert_eq!(new_contents, "[package]\n\
+                                  name = \"my-game\"\n\
+                                  # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html\n\
+                                  \n\
+                                  [dependencies]  # the game's dependencies\n\
+                                  serde = \"1.0\" # for the save files\n\
+                                  wasm-rgame = \"0.3.1\"\n\
+                                  \n\
+                                  # Faster release builds\n\
+                                  [profile.release]\n\
+                                  lto = true\n");
+    }
+
+    #[test]
+    fn merge_dependencies_rejects_non_dependencies() {
+        let contents = format!("{}serde = \"1.0\"\n", CARGO_INIT_TOML);
+        assert!(merge_dependencies(&contents, "wasm-rgame = { version = \"0.3\", default-features = false }\n").is_ok());
+        assert!(merge_dependencies(&contents, "wasm-rgame = \"0.3\"\n\n[lib]\ncrate-type = [\"cdylib\"]\n").is_err());
+        assert!(merge_dependencies(&contents, "features = [\"wasm\"]\n").is_err());
+    }
+
+    #[test]
+    fn merge_dependencies_creates_table() {
+        let new_contents = merge_dependencies("[package]\nname = \"my-game\"\n", "wasm-rgame = \"*\"\n").unwrap();
+
+        assert_eq!(new_contents.matches("[dependencies]").count(), 1);
+        let value = new_contents.parse::<toml::Value>().unwrap();
+        assert_eq!(value["dependencies"]["wasm-rgame"].as_str(), Some("*"));
+    }
+
+    #[test]
+    fn ensure_lib_crate_type_leaves_correct_lib_untouched() {
+        let contents = format!("{}\n[lib]\ncrate-type = [\"cdylib\"]\n", CARGO_INIT_TOML);
+        assert!(ensure_lib_crate_type(&contents).unwrap().is_none());
+    }
+
+    #[test]
+    fn ensure_lib_crate_type_fixes_existing_lib() {
+        let contents = format!("{}\n[lib]\npath = \"src/lib.rs\"\ncrate-type = [\"rlib\"]\n", CARGO_INIT_TOML);
+        let new_contents = ensure_lib_crate_type(&contents).unwrap().expect("crate-type is changed");
+
+        let value = new_contents.parse::<toml::Value>().unwrap();
+        assert_eq!(value["lib"]["path"].as_str(), Some("src/lib.rs"));
+        assert!(CargoToml::from_str(&new_contents).unwrap().has_lib_crate_type("cdylib"));
+    }
+
+    #[test]
+    fn template_files_use_built_project_name() {
+        let template_files = Templates::embedded().files("my_game");
+
+        let paths = template_files.iter().map(|t| t.path).collect::<Vec<_>>();
+        assert_eq!(paths, vec!["src/lib.rs", "src/bootstrap.rs", "src/simple_box.rs"]);
+        assert!(!template_files[0].contents.contains("$PROJECT_NAME"));
+    }
+
+    #[test]
+    fn templates_from_dir_requires_every_template() {
+        let tmp_dir = TempDir::new().unwrap();
+        for file_name in &["lib.rs.template", "bootstrap.rs.template", "simple_box.rs.template"] {
+            fs::write(tmp_dir.path().join(file_name), "// template").unwrap();
+        }
+        assert!(Templates::from_dir(tmp_dir.path()).is_err());
+
+        fs::write(tmp_dir.path().join("cargo_toml.append"), "wasm-rgame = \"0.3\"\n").unwrap();
+        let templates = Templates::from_dir(tmp_dir.path()).unwrap();
+        assert_eq!(templates.cargo_toml_append, "wasm-rgame = \"0.3\"\n");
+    }
+
+    #[test]
+    fn dry_run_templates_use_placeholder_unless_pinned() {
+        let templates = dry_run_templates(true, None);
+        assert!(templates.cargo_toml_append.contains("wasm-rgame = \"<latest wasm-rgame version>\""));
+
+        let templates = dry_run_templates(false, Some("0.3.1".to_owned()));
+        assert!(templates.cargo_toml_append.contains("wasm-rgame = \"0.3.1\""));
+    }
+
+    #[test]
+    fn set_wasm_rgame_version_substitutes_dependency_version() {
+        let mut templates = Templates::embedded();
+        templates.set_wasm_rgame_version("0.3.1");
+
+        let contents = format!("{}{}", CARGO_INIT_TOML, templates.cargo_toml_append);
+        let cargo_toml = CargoToml::from_str(&contents).unwrap();
+        assert_eq!(cargo_toml.dependency_req("wasm-rgame"), Some(semver::VersionReq::parse("0.3.1").unwrap()));
+    }
+}
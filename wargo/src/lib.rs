@@ -0,0 +1,490 @@
+//! The wasm-rgame project tooling behind the `wargo` binary, which can also be
+//! embedded in other tools. Progress is reported through the `log` facade and
+//! failures are returned as (downcastable) `error::WargoError`s.
+#[macro_use] extern crate failure;
+#[macro_use] extern crate log;
+#[macro_use] extern crate serde_derive;
+extern crate atty;
+extern crate cargo_lock;
+extern crate cargo_toml;
+extern crate flate2;
+extern crate fs2;
+extern crate futures;
+extern crate hubcaps;
+extern crate indicatif;
+extern crate reqwest;
+extern crate semver;
+extern crate serde;
+extern crate serde_json;
+extern crate tar;
+extern crate tempfile;
+extern crate tokio_core;
+extern crate toml;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::str;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use flate2::read::GzDecoder;
+use hubcaps::{Credentials, Github};
+use semver::Version;
+use tempfile::TempDir;
+use tokio_core::reactor::Core;
+
+use build::BindgenTarget;
+use cargo_toml::{CargoToml, Workspace};
+use error::WargoError;
+use progress::ProgressMode;
+use release::ReleaseRepo;
+
+/// Logs the warning and counts it for `deny_warnings`, so the warnings are counted
+/// no matter how (or if) the logger is set up.
+macro_rules! wargo_warn {
+    ($($arg:tt)*) => {{
+        warn!($($arg)*);
+        $crate::count_warning();
+    }};
+}
+
+pub mod build;
+pub mod config;
+pub mod error;
+pub mod info;
+pub mod init;
+mod metadata;
+pub mod progress;
+pub mod release;
+mod retry;
+mod tool;
+pub mod update;
+
+pub type Result<T> = std::result::Result<T, failure::Error>;
+
+pub const DEFAULT_RETRIES : u32 = 3;
+
+/// The number of warnings `wargo_warn!` logged in the current build.
+static WARNING_COUNT : AtomicUsize = AtomicUsize::new(0);
+
+fn count_warning() {
+    WARNING_COUNT.fetch_add(1, Ordering::SeqCst);
+}
+
+fn warning_count() -> usize {
+    WARNING_COUNT.load(Ordering::SeqCst)
+}
+
+/// Starts counting the warnings of a new build.
+fn reset_warning_count() {
+    WARNING_COUNT.store(0, Ordering::SeqCst);
+}
+
+/// Finds the nearest directory (starting with `dir` itself) that contains the file.
+fn find_in_ancestors(dir: &Path, file_name: &str) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|ancestor| ancestor.join(file_name).is_file())
+        .map(|ancestor| ancestor.to_path_buf())
+}
+
+/// Moves into the nearest directory with a Cargo.toml, so the project files
+/// (and the target directory) are found relative to the project root.
+pub fn enter_project_dir() -> Result<()> {
+    let current_dir = env::current_dir()
+        .map_err(|err| format_err!("Could not find the current directory, error: {}", err))?;
+
+    let project_dir = find_in_ancestors(&current_dir, "Cargo.toml")
+        .ok_or_else(|| WargoError::ManifestNotFoundInAncestors { dir: current_dir.clone() })?;
+    if project_dir != current_dir {
+        debug!("Using the project in {:?}.\n", project_dir);
+        env::set_current_dir(&project_dir)
+            .map_err(|err| format_err!("Could not move into the project directory: {:?}, error: {}", project_dir, err))?;
+    }
+
+    Ok(())
+}
+
+/// Moves into the directory of the Cargo.toml at `manifest_path`, like cargo's
+/// `--manifest-path`.
+pub fn enter_manifest_dir(manifest_path: &Path) -> Result<()> {
+    if manifest_path.file_name() != Some("Cargo.toml".as_ref()) || !manifest_path.is_file() {
+        return Err(WargoError::ManifestPathInvalid { path: manifest_path.to_path_buf() }.into());
+    }
+
+    let project_dir = match manifest_path.parent() {
+        Some(project_dir) if project_dir != Path::new("") => project_dir,
+        _ => return Ok(()),
+    };
+
+    debug!("Using the project in {:?}.\n", project_dir);
+    env::set_current_dir(project_dir)
+        .map_err(|err| format_err!("Could not move into the project directory: {:?}, error: {}", project_dir, err))?;
+
+    Ok(())
+}
+
+/// The Cargo.lock of the project, which is at the workspace root for workspace
+/// members. Defaults to the project directory when it isn't a workspace member.
+fn cargo_lock_path() -> PathBuf {
+    env::current_dir().ok()
+        .and_then(|current_dir| workspace_root(&current_dir))
+        .map(|dir| dir.join("Cargo.lock"))
+        .unwrap_or_else(|| PathBuf::from("Cargo.lock"))
+}
+
+/// The nearest ancestor of the project with a `[workspace]` that includes the
+/// project. Like cargo, only the nearest `[workspace]` is considered, an outer
+/// project (or its Cargo.lock) that doesn't include the project is ignored.
+fn workspace_root(project_dir: &Path) -> Option<PathBuf> {
+    let (root, workspace) = project_dir.ancestors().skip(1)
+        .filter_map(|ancestor| {
+            fs::read_to_string(ancestor.join("Cargo.toml")).ok()
+                .and_then(|contents| Workspace::from_manifest(&contents))
+                .map(|workspace| (ancestor, workspace))
+        })
+        .next()?;
+
+    let member_path = project_dir.strip_prefix(root).ok()?
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    if workspace.includes(&member_path) {
+        Some(root.to_path_buf())
+    } else {
+        None
+    }
+}
+
+fn cargo_toml() -> Result<CargoToml> {
+    let mut cargo_file = File::open("Cargo.toml")
+        .map_err(|error| WargoError::ManifestNotFound { error })?;
+
+    let mut cargo_contents = String::new();
+    let _ = cargo_file.read_to_string(&mut cargo_contents)
+        .map_err(|err| format_err!("Cannot read Cargo.toml contents, error: {}", err))?;
+
+    if CargoToml::is_virtual_manifest(&cargo_contents) {
+        return Err(WargoError::VirtualManifest.into());
+    }
+
+    let cargo_toml = CargoToml::from_str(&cargo_contents)
+        .map_err(|error| WargoError::ManifestParse { error })?;
+
+    Ok(cargo_toml)
+}
+
+fn project_name() -> Result<String> {
+    Ok(cargo_toml()?.package.name.to_owned())
+}
+
+/// wasm-bindgen requires the project to be built as a `cdylib`, check
+/// the Cargo.toml early so the user doesn't fail deep in the build.
+fn check_cdylib_crate_type() -> Result<()> {
+    if !cargo_toml()?.has_lib_crate_type("cdylib") {
+        return Err(WargoError::NotCdylib.into());
+    }
+
+    Ok(())
+}
+
+/// The name of the built wasm artifact (and the wasm-bindgen output), which
+/// is the crate name cargo normalizes from the manifest.
+fn built_project_name() -> Result<String> {
+    Ok(cargo_toml()?.lib_name())
+}
+
+/// Generates the Cargo.lock if it is missing and warns if it looks out of date,
+/// since the wasm-rgame version is read from it.
+fn ensure_cargo_lock() -> Result<()> {
+    ensure_cargo_lock_with(Path::new("Cargo.toml"), &cargo_lock_path(), || {
+        execute_command(
+            &tool::cargo(),
+            "generate-lockfile",
+            "Generate the Cargo.lock to find the wasm-rgame version"
+        )
+    })
+}
+
+/// If generating the lockfile fails, reading the Cargo.lock afterwards will
+/// report the missing file as before.
+fn ensure_cargo_lock_with(
+    cargo_toml_path: &Path,
+    cargo_lock_path: &Path,
+    generate_lockfile: impl FnOnce() -> Result<()>,
+) -> Result<()> {
+    if !cargo_lock_path.exists() {
+        info!("Generating missing Cargo.lock.. ");
+        match generate_lockfile() {
+            Ok(()) => info!("done!\n"),
+            Err(err) => wargo_warn!("failed!\nCould not generate the Cargo.lock, error: {}\n", err),
+        }
+        return Ok(());
+    }
+
+    if cargo_lock_outdated(cargo_toml_path, cargo_lock_path) {
+        wargo_warn!("Cargo.toml was modified after Cargo.lock, the wasm-rgame version may be out of date. \
+               Run `cargo update -p wasm-rgame` or `cargo build` to update the Cargo.lock.\n");
+    }
+
+    Ok(())
+}
+
+/// Whether the locked wasm-rgame version may not match the Cargo.toml. Cargo doesn't
+/// rewrite an unchanged Cargo.lock, so a newer Cargo.toml is only outdated if the
+/// locked version doesn't satisfy the wasm-rgame requirement.
+fn cargo_lock_outdated(cargo_toml_path: &Path, cargo_lock_path: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    match (modified(cargo_toml_path), modified(cargo_lock_path)) {
+        (Ok(cargo_toml_modified), Ok(cargo_lock_modified)) if cargo_toml_modified > cargo_lock_modified => (),
+        _ => return false,
+    }
+
+    let (cargo_toml_contents, cargo_lock_contents) = match (fs::read_to_string(cargo_toml_path), fs::read_to_string(cargo_lock_path)) {
+        (Ok(cargo_toml_contents), Ok(cargo_lock_contents)) => (cargo_toml_contents, cargo_lock_contents),
+        _ => return true,
+    };
+    let wasm_rgame_req = match CargoToml::from_str(&cargo_toml_contents) {
+        // Git / path dependencies without a version can't be compared
+        Ok(cargo_toml) => match cargo_toml.dependency_req("wasm-rgame") {
+            Some(wasm_rgame_req) => wasm_rgame_req,
+            None => return false,
+        },
+        Err(_) => return true,
+    };
+
+    match cargo_lock::find_version_normalized("wasm-rgame", &cargo_lock_contents) {
+        Some(wasm_rgame_version) => !wasm_rgame_req.matches(&wasm_rgame_version),
+        None => true,
+    }
+}
+
+/// The wasm-rgame version the project is using, from the Cargo.lock. Falls back
+/// to `cargo metadata` when the version can't be found in the Cargo.lock.
+pub fn wasm_rgame_version() -> Result<Version> {
+    let cargo_lock_contents = fs::read_to_string(cargo_lock_path())
+        .map_err(|error| WargoError::LockNotFound { error })?;
+
+    if let Some(version) = cargo_lock::find_version_normalized("wasm-rgame", &cargo_lock_contents) {
+        return Ok(version);
+    }
+
+    match metadata::find_version("wasm-rgame") {
+        Ok(Some(version)) => Ok(version),
+        Ok(None) => Err(WargoError::LockParse.into()),
+        Err(err) => {
+            debug!("Could not find the wasm-rgame version with `cargo metadata`, error: {}\n", err);
+            Err(WargoError::LockParse.into())
+        },
+    }
+}
+
+/// Executes the command with process::Command, mapping both the error of
+/// executing the command and the status code + output to a Failure::Error
+fn execute_command(command: &str, args: &str, context: &str) -> Result<()> {
+    execute_command_args(command, &args.split_whitespace().collect::<Vec<_>>(), context)
+}
+
+/// Same as `execute_command`, but with each argument passed separately so
+/// arguments are not split on whitespace.
+fn execute_command_args<S: AsRef<str>>(command: &str, args: &[S], context: &str) -> Result<()> {
+    run_command(command, args, context)?;
+    Ok(())
+}
+
+/// Same as `execute_command`, but returns the (trimmed) stdout of the command
+/// for the callers that need to parse it.
+fn capture_command(command: &str, args: &str, context: &str) -> Result<String> {
+    let output = run_command(command, &args.split_whitespace().collect::<Vec<_>>(), context)?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Runs the command to completion, erroring if it couldn't be executed or
+/// exited with a non-zero status.
+fn run_command<S: AsRef<str>>(command: &str, args: &[S], context: &str) -> Result<Output> {
+    run_command_with_env(command, args, &[], context)
+}
+
+/// Same as `run_command` with the environment variables set for the command, the
+/// variables aren't part of the errors so they can be used for secrets.
+fn run_command_with_env<S: AsRef<str>>(command: &str, args: &[S], envs: &[(&str, String)], context: &str) -> Result<Output> {
+    let args = args.iter().map(|arg| arg.as_ref()).collect::<Vec<_>>();
+    let output = Command::new(command)
+        .args(&args)
+        .envs(envs.iter().map(|&(key, ref value)| (key, value)))
+        .output()
+        .map_err(|error| WargoError::CommandNotExecuted {
+            command: format!("{} {}", command, args.join(" ")),
+            context: context.to_owned(),
+            error,
+        })?;
+
+    if !output.status.success() {
+        return Err(WargoError::CommandFailed {
+            command: format!("{} {}", command, args.join(" ")),
+            context: context.to_owned(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }.into());
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn find_in_ancestors_walks_up_to_nearest_match() {
+        let tmp_dir = TempDir::new().unwrap();
+        let project_dir = tmp_dir.path().join("my-game");
+        let nested_dir = project_dir.join("src").join("levels");
+        fs::create_dir_all(&nested_dir).unwrap();
+        fs::write(project_dir.join("Cargo.toml"), "").unwrap();
+        fs::write(tmp_dir.path().join("Cargo.toml"), "").unwrap();
+        fs::write(tmp_dir.path().join("Cargo.lock"), "").unwrap();
+
+        assert_eq!(find_in_ancestors(&nested_dir, "Cargo.toml"), Some(project_dir.clone()));
+        assert_eq!(find_in_ancestors(&project_dir, "Cargo.toml"), Some(project_dir.clone()));
+        assert_eq!(find_in_ancestors(&nested_dir, "Cargo.lock"), Some(tmp_dir.path().to_path_buf()));
+        assert_eq!(find_in_ancestors(&nested_dir, "wargo-file-that-does-not-exist"), None);
+    }
+
+    #[test]
+    fn workspace_root_requires_the_project_to_be_a_member() {
+        let tmp_dir = TempDir::new().unwrap();
+        let project_dir = tmp_dir.path().join("games").join("my-game");
+        fs::create_dir_all(&project_dir).unwrap();
+        fs::write(project_dir.join("Cargo.toml"), "[package]\nname = \"my-game\"\n").unwrap();
+
+        // An unrelated outer project's Cargo.lock isn't the project's
+        fs::write(tmp_dir.path().join("Cargo.toml"), "[package]\nname = \"outer\"\n").unwrap();
+        fs::write(tmp_dir.path().join("Cargo.lock"), "").unwrap();
+        assert_eq!(workspace_root(&project_dir), None);
+
+        fs::write(tmp_dir.path().join("Cargo.toml"), "[workspace]\nmembers = [\"tools\"]\n").unwrap();
+        assert_eq!(workspace_root(&project_dir), None);
+
+        fs::write(tmp_dir.path().join("Cargo.toml"), "[workspace]\nmembers = [\"games/*\"]\n").unwrap();
+        assert_eq!(workspace_root(&project_dir), Some(tmp_dir.path().to_path_buf()));
+    }
+
+    #[test]
+    fn enter_manifest_dir_requires_cargo_toml_file() {
+        let tmp_dir = TempDir::new().unwrap();
+        fs::write(tmp_dir.path().join("wargo.toml"), "").unwrap();
+        fs::create_dir(tmp_dir.path().join("Cargo.toml")).unwrap();
+
+        for manifest_path in &[tmp_dir.path().join("wargo.toml"), tmp_dir.path().join("Cargo.toml"), tmp_dir.path().join("missing/Cargo.toml")] {
+            match enter_manifest_dir(manifest_path).unwrap_err().downcast_ref::<WargoError>() {
+                Some(WargoError::ManifestPathInvalid { path }) => assert_eq!(path, manifest_path),
+                _ => panic!("expected ManifestPathInvalid for {:?}", manifest_path),
+            }
+        }
+    }
+
+    #[test]
+    fn wargo_warn_counts_without_a_logger() {
+        let count_before = warning_count();
+        wargo_warn!("a warning from the tests\n");
+
+        // Other tests may warn at the same time
+        assert!(warning_count() > count_before);
+    }
+
+    #[test]
+    fn execute_command_reports_error_kinds() {
+        let err = execute_command("wargo-command-that-does-not-exist", "--version", "test").unwrap_err();
+        match err.downcast_ref::<WargoError>() {
+            Some(WargoError::CommandNotExecuted { command, .. }) => assert_eq!(command, "wargo-command-that-does-not-exist --version"),
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        let err = execute_command("cargo", "--not-a-real-flag", "test").unwrap_err();
+        match err.downcast_ref::<WargoError>() {
+            Some(WargoError::CommandFailed { context, .. }) => assert_eq!(context, "test"),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn capture_command_returns_trimmed_stdout() {
+        let stdout = capture_command("cargo", "--version", "test").unwrap();
+        assert!(stdout.starts_with("cargo "));
+        assert!(!stdout.ends_with('\n'));
+
+        let err = capture_command("cargo", "--not-a-real-flag", "test").unwrap_err();
+        match err.downcast_ref::<WargoError>() {
+            Some(WargoError::CommandFailed { context, .. }) => assert_eq!(context, "test"),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ensure_cargo_lock_generates_missing_lock() {
+        let tmp_dir = TempDir::new().unwrap();
+        let cargo_toml_path = tmp_dir.path().join("Cargo.toml");
+        let cargo_lock_path = tmp_dir.path().join("Cargo.lock");
+
+        ensure_cargo_lock_with(&cargo_toml_path, &cargo_lock_path, || {
+            fs::write(&cargo_lock_path, include_str!("../../cargo-lock/data/Cargo.lock.example"))?;
+            Ok(())
+        }).unwrap();
+
+        let cargo_lock_contents = fs::read_to_string(&cargo_lock_path).unwrap();
+        assert_eq!(cargo_lock::find_version_normalized("wasm-rgame", &cargo_lock_contents), Some(Version::parse("0.3.1").unwrap()));
+    }
+
+    #[test]
+    fn ensure_cargo_lock_tolerates_failed_generation() {
+        let tmp_dir = TempDir::new().unwrap();
+        let cargo_toml_path = tmp_dir.path().join("Cargo.toml");
+        let cargo_lock_path = tmp_dir.path().join("Cargo.lock");
+
+        let result = ensure_cargo_lock_with(&cargo_toml_path, &cargo_lock_path, || Err(format_err!("offline")));
+        assert!(result.is_ok());
+        assert!(!cargo_lock_path.exists());
+    }
+
+    #[test]
+    fn cargo_lock_outdated_only_when_requirement_is_not_satisfied() {
+        let tmp_dir = TempDir::new().unwrap();
+        let cargo_toml_path = tmp_dir.path().join("Cargo.toml");
+        let cargo_lock_path = tmp_dir.path().join("Cargo.lock");
+        fs::write(&cargo_lock_path, include_str!("../../cargo-lock/data/Cargo.lock.example")).unwrap();
+        fs::write(&cargo_toml_path, "[package]\nname = \"my-game\"\n\n[dependencies]\nwasm-rgame = \"0.3\"\n").unwrap();
+        File::options().write(true).open(&cargo_toml_path).unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(60)).unwrap();
+        assert!(!cargo_lock_outdated(&cargo_toml_path, &cargo_lock_path));
+
+        // The Cargo.toml is set to be clearly newer, even on file systems with coarse mtimes
+        let write_newer_cargo_toml = |contents: &str| {
+            fs::write(&cargo_toml_path, contents).unwrap();
+            File::options().write(true).open(&cargo_toml_path).unwrap()
+                .set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        };
+
+        // Ex. the package version was bumped, the lock still has a wasm-rgame 0.3.1
+        write_newer_cargo_toml("[package]\nname = \"my-game\"\nversion = \"0.2.0\"\n\n[dependencies]\nwasm-rgame = \"0.3\"\n");
+        assert!(!cargo_lock_outdated(&cargo_toml_path, &cargo_lock_path));
+
+        write_newer_cargo_toml("[package]\nname = \"my-game\"\n\n[dependencies]\nwasm-rgame = \"0.4\"\n");
+        assert!(cargo_lock_outdated(&cargo_toml_path, &cargo_lock_path));
+    }
+
+    #[test]
+    fn ensure_cargo_lock_does_not_regenerate_existing_lock() {
+        let tmp_dir = TempDir::new().unwrap();
+        let cargo_toml_path = tmp_dir.path().join("Cargo.toml");
+        let cargo_lock_path = tmp_dir.path().join("Cargo.lock");
+        fs::write(&cargo_lock_path, "").unwrap();
+
+        ensure_cargo_lock_with(&cargo_toml_path, &cargo_lock_path, || panic!("should not generate")).unwrap();
+    }
+}
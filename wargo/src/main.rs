@@ -1,51 +1,193 @@
 #[macro_use] extern crate structopt;
 #[macro_use] extern crate failure;
 #[macro_use] extern crate log;
-extern crate cargo_lock;
-extern crate cargo_toml;
 extern crate env_logger;
-extern crate flate2;
-extern crate futures;
 extern crate hubcaps;
-extern crate reqwest;
-extern crate semver;
-extern crate tar;
-extern crate tempfile;
-extern crate tokio_core;
+extern crate wargo;
 
 use std::env;
-use std::fs::{self, File, DirBuilder};
-use std::io::{Read, Write};
+use std::ffi::OsString;
+use std::fs::DirBuilder;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
-use std::process::{Command, exit};
-use std::str;
+use std::process::exit;
+use std::str::FromStr;
 
-use flate2::read::GzDecoder;
-use hubcaps::Github;
-use log::LevelFilter;
-use semver::Version;
+use env_logger::WriteStyle;
+use env_logger::fmt::Color;
+use hubcaps::Credentials;
+use log::{Level, LevelFilter};
 use structopt::StructOpt;
-use tempfile::TempDir;
-use tokio_core::reactor::Core;
+use structopt::clap::{AppSettings, Shell};
 
-use cargo_toml::CargoToml;
+use wargo::{build, info, init, release, update, Result, DEFAULT_RETRIES};
+use wargo::build::BindgenTarget;
+use wargo::config::{BuildConfig, WargoConfig};
+use wargo::info::InfoFormat;
+use wargo::progress::ProgressMode;
+use wargo::release::ReleaseRepo;
 
-mod build;
-mod init;
+#[derive(Debug, StructOpt)]
+#[structopt(name = "wargo", about = "Tool used with wasm-rgame projects.")]
+struct Args {
+    /// Color the log output: auto|always|never. `auto` only colors the output when
+    /// it's a terminal and NO_COLOR isn't set. [default: auto]
+    #[structopt(long = "color", raw(global = "true"))]
+    color: Option<ColorMode>,
+
+    /// Path to the Cargo.toml of the project, defaults to the nearest Cargo.toml
+    /// of the current directory or its parents.
+    #[structopt(long = "manifest-path", parse(from_os_str), raw(global = "true"))]
+    manifest_path: Option<PathBuf>,
 
-type Result<T> = std::result::Result<T, failure::Error>;
+    #[structopt(subcommand)]
+    command: Opt,
+}
 
 #[derive(Debug, StructOpt)]
-#[structopt(name = "wargo", about = "Tool used with wasm-rgame projects.")]
 enum Opt {
     /// Build the current project, packing the output wasm file with all
     /// the additional Javascript / HTML.
     #[structopt(name = "build")]
     Build {
         /// Use a local path for the js files, defaults to downloading the latest
-        /// matching release.
-        #[structopt(long = "js-path", parse(from_os_str))]
+        /// matching release. Can also be set with the WARGO_JS_PATH environment variable.
+        #[structopt(long = "js-path", env = "WARGO_JS_PATH", parse(from_os_str))]
         js_path: Option<PathBuf>,
+
+        /// Use a specific release of wasm-rgame-js (tag or version, ex. `v0.1.0`)
+        /// instead of choosing the latest matching release automatically.
+        #[structopt(long = "js-version")]
+        js_version: Option<String>,
+
+        /// Show a spinner during the long-running build steps: auto|always|never.
+        /// `auto` only shows the spinner when the output is a terminal. [default: auto]
+        #[structopt(long = "progress")]
+        progress: Option<ProgressMode>,
+
+        /// Print how long each phase of the build took.
+        #[structopt(long = "timings")]
+        timings: bool,
+
+        /// Keep the downloaded wasm-rgame-js release files in their temporary
+        /// directory instead of cleaning them up, useful for debugging.
+        #[structopt(long = "keep-temp")]
+        keep_temp: bool,
+
+        /// How many times to retry fetching the wasm-rgame-js release when
+        /// the network fails with a transient error. [default: 3]
+        #[structopt(long = "retries")]
+        retries: Option<u32>,
+
+        /// Always download the source tarball of the wasm-rgame-js release, even
+        /// if the release has a pre-built bundle attached.
+        #[structopt(long = "prefer-source")]
+        prefer_source: bool,
+
+        /// How many leading path components to strip when unpacking the release
+        /// tarball. By default the tarball's single top-level directory is used.
+        #[structopt(long = "strip-components")]
+        strip_components: Option<usize>,
+
+        /// Fetch the wasm-rgame-js release with `git clone` instead of the GitHub
+        /// API, which is also done when the API or the download fails.
+        #[structopt(long = "use-git")]
+        use_git: bool,
+
+        /// The kind of JS output wasm-bindgen generates: no-modules|web|bundler.
+        /// The wasm-rgame-js templates expect `no-modules`. [default: no-modules]
+        #[structopt(long = "bindgen-target")]
+        bindgen_target: Option<BindgenTarget>,
+
+        /// Have wasm-bindgen generate TypeScript definitions.
+        #[structopt(long = "typescript")]
+        typescript: bool,
+
+        /// The GitHub repository to download the js releases from, in the form
+        /// `owner/repo`. [default: DarrenTsung/wasm-rgame-js]
+        #[structopt(long = "js-repo")]
+        js_repo: Option<String>,
+
+        /// The API url of a GitHub Enterprise host to download the js releases
+        /// from (ex. `https://github.example.com/api/v3`), defaults to github.com.
+        #[structopt(long = "github-host", env = "WARGO_GITHUB_HOST")]
+        github_host: Option<String>,
+
+        /// The GitHub token used to access private js repositories.
+        #[structopt(long = "github-token", env = "WARGO_GITHUB_TOKEN", raw(hide_env_values = "true"))]
+        github_token: Option<String>,
+
+        /// After building, pack the output directory into a .tar.gz for deployment.
+        #[structopt(long = "package")]
+        package: bool,
+
+        /// Build the lib target, this is the default unless the project has `[[bin]]` targets.
+        #[structopt(long = "lib")]
+        lib: bool,
+
+        /// Build the named `[[bin]]` target instead of the lib, bundled into `target/wasm-rgame/<name>`.
+        #[structopt(long = "bin")]
+        bin: Option<String>,
+
+        /// Build the named example from the `examples/` directory instead of the lib.
+        #[structopt(long = "example")]
+        example: Option<String>,
+
+        /// Build with the release profile, the same as `--profile release`.
+        #[structopt(long = "release", raw(conflicts_with = "\"profile\""))]
+        release: bool,
+
+        /// Build with the named cargo profile (ex. a custom `release-wasm` profile)
+        /// and bundle the wasm from its output directory. [default: dev]
+        #[structopt(long = "profile")]
+        profile: Option<String>,
+
+        /// Skip the rustup setup and `cargo build`, bundling the wasm that was
+        /// already built (ex. in a separate CI step).
+        #[structopt(long = "no-build")]
+        no_build: bool,
+
+        /// Fail the build if any warnings were emitted.
+        #[structopt(long = "deny-warnings")]
+        deny_warnings: bool,
+
+        /// The entry HTML file of the bundled project, reported at the end of the
+        /// build. Files are not renamed. [default: index.html]
+        #[structopt(long = "index-name")]
+        index_name: Option<String>,
+
+        /// Fetch the wasm-rgame-js releases even if they were recently cached.
+        #[structopt(long = "refresh-releases")]
+        refresh_releases: bool,
+
+        /// Pass `--locked` to cargo, the build fails if the Cargo.lock would change.
+        #[structopt(long = "locked")]
+        locked: bool,
+
+        /// Pass `--frozen` to cargo, like `--locked` but also without network access.
+        #[structopt(long = "frozen")]
+        frozen: bool,
+
+        /// A shell command to run in the output directory after a successful build,
+        /// with WARGO_OUTPUT_DIR, WARGO_PROJECT_NAME and WARGO_VERSION set. The build
+        /// fails if the command fails.
+        #[structopt(long = "post-build")]
+        post_build: Option<String>,
+
+        /// Build the project, then print the wasm-bindgen command that would be run
+        /// (quoted to be copy-pasted) instead of running it.
+        #[structopt(long = "print-bindgen-cmd")]
+        print_bindgen_cmd: bool,
+
+        /// Remove the output of every project and example in target/wasm-rgame
+        /// before bundling, not only the output of the built target.
+        #[structopt(long = "force-clean")]
+        force_clean: bool,
+
+        /// Don't wait for other builds of the project to finish. Concurrent builds
+        /// can remove each other's output.
+        #[structopt(long = "no-lock")]
+        no_lock: bool,
     },
     /// Initialize the current directory as a wasm-rgame project.
     #[structopt(name = "init")]
@@ -53,6 +195,24 @@ enum Opt {
         /// Set the resulting package name, defaults to the directory name.
         #[structopt(long = "name")]
         name: Option<String>,
+
+        /// Print the files that would be created / overwritten and the dependencies
+        /// that would be added, without changing anything.
+        #[structopt(long = "dry-run")]
+        dry_run: bool,
+
+        /// Use the templates from the latest wasm-rgame-js release instead of the
+        /// built-in templates, falling back to the built-in ones if they can't be fetched.
+        #[structopt(long = "templates-from-release")]
+        templates_from_release: bool,
+
+        /// The wasm-rgame version to add to the Cargo.toml, defaults to the version
+        /// of the latest wasm-rgame-js release.
+        #[structopt(long = "wasm-rgame-version")]
+        wasm_rgame_version: Option<String>,
+
+        #[structopt(flatten)]
+        release_repo: ReleaseRepoOpt,
     },
     /// Create a new cargo package at <path> and initialize it.
     #[structopt(name = "new")]
@@ -64,32 +224,293 @@ enum Opt {
         /// The path to create the new cargo package at.
         #[structopt(parse(from_os_str))]
         path: PathBuf,
+
+        /// Print the files that would be created / overwritten and the dependencies
+        /// that would be added, without changing anything.
+        #[structopt(long = "dry-run")]
+        dry_run: bool,
+
+        /// Use the templates from the latest wasm-rgame-js release instead of the
+        /// built-in templates, falling back to the built-in ones if they can't be fetched.
+        #[structopt(long = "templates-from-release")]
+        templates_from_release: bool,
+
+        /// The wasm-rgame version to add to the Cargo.toml, defaults to the version
+        /// of the latest wasm-rgame-js release.
+        #[structopt(long = "wasm-rgame-version")]
+        wasm_rgame_version: Option<String>,
+
+        #[structopt(flatten)]
+        release_repo: ReleaseRepoOpt,
     },
+    /// List the available wasm-rgame-js releases, marking the one that would be
+    /// chosen for the current project.
+    #[structopt(name = "list-releases")]
+    ListReleases {
+        #[structopt(flatten)]
+        release_repo: ReleaseRepoOpt,
+    },
+    /// Update the wasm-rgame dependency to the latest release within the
+    /// Cargo.toml version requirement.
+    #[structopt(name = "update")]
+    Update {
+        #[structopt(flatten)]
+        release_repo: ReleaseRepoOpt,
+
+        /// Only report what would be updated.
+        #[structopt(long = "dry-run")]
+        dry_run: bool,
+    },
+    /// Print the versions of wargo, wasm-rgame, wasm-bindgen and the rust toolchain,
+    /// and check that the environment can build a project.
+    #[structopt(name = "info")]
+    Info {
+        /// How to print the diagnostics: human|json. [default: human]
+        #[structopt(long = "format")]
+        format: Option<InfoFormat>,
+    },
+    /// Write a shell completion script for wargo to stdout.
+    #[structopt(name = "completions", raw(setting = "AppSettings::Hidden"))]
+    Completions {
+        /// The shell to generate completions for.
+        #[structopt(raw(possible_values = "&Shell::variants()"))]
+        shell: Shell,
+    },
+}
+
+/// Options for the commands that fetch the wasm-rgame-js releases.
+#[derive(Debug, StructOpt)]
+struct ReleaseRepoOpt {
+    /// The GitHub repository to fetch the releases from. [default: DarrenTsung/wasm-rgame-js]
+    #[structopt(long = "js-repo")]
+    js_repo: Option<String>,
+
+    /// The API url of a GitHub Enterprise host to fetch the releases from.
+    #[structopt(long = "github-host", env = "WARGO_GITHUB_HOST")]
+    github_host: Option<String>,
+
+    /// A GitHub token used to fetch the releases, for private repositories.
+    #[structopt(long = "github-token", env = "WARGO_GITHUB_TOKEN", raw(hide_env_values = "true"))]
+    github_token: Option<String>,
+
+    /// How many times to retry fetching the releases. [default: 3]
+    #[structopt(long = "retries")]
+    retries: Option<u32>,
+
+    /// Fetch the releases even if they were recently cached.
+    #[structopt(long = "refresh-releases")]
+    refresh_releases: bool,
+}
+
+impl ReleaseRepoOpt {
+    /// Command-line flags take precedence over the wargo.toml. The releases are only
+    /// cached in the project's target directory by the commands that operate on the
+    /// project, `wargo new` could be run inside of an unrelated project.
+    fn into_release_repo(self, file_config: BuildConfig, in_project: bool) -> ReleaseRepo {
+        let cache_path = if in_project {
+            Some(PathBuf::from(release::RELEASES_CACHE_PATH))
+        } else {
+            None
+        };
+
+        ReleaseRepo {
+            repo: self.js_repo.or(file_config.js_repo).unwrap_or_else(|| release::DEFAULT_JS_REPO.to_owned()),
+            github_host: self.github_host.or(file_config.github_host),
+            credentials: self.github_token.map(Credentials::Token),
+            retries: self.retries.or(file_config.retries).unwrap_or(DEFAULT_RETRIES),
+            cache_path,
+            refresh: self.refresh_releases,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<ColorMode, String> {
+        match s {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(format!("Invalid color mode: `{}`, expected one of: auto, always, never", s)),
+        }
+    }
+}
+
+impl ColorMode {
+    fn write_style(self, no_color: bool) -> WriteStyle {
+        match self {
+            ColorMode::Auto if no_color => WriteStyle::Never,
+            ColorMode::Auto => WriteStyle::Auto,
+            ColorMode::Always => WriteStyle::Always,
+            ColorMode::Never => WriteStyle::Never,
+        }
+    }
 }
 
 fn main() {
-    env_logger::Builder::new()
-        .format(|buf, record| write!(buf, "{}", record.args()))
-        .filter_level(LevelFilter::Info)
-        .init();
+    let args = Args::from_iter(strip_cargo_subcommand(env::args_os().collect()));
+    init_logger(args.color.unwrap_or(ColorMode::Auto));
 
-    if let Err(err) = main_ty() {
+    if let Err(err) = main_ty(args.command, args.manifest_path) {
         error!("{}", err);
         exit(1);
     }
 }
 
-fn main_ty() -> Result<()> {
-    match Opt::from_args() {
-        Opt::Build { js_path } => {
+fn init_logger(color: ColorMode) {
+    let mut logger = env_logger::Builder::new();
+    logger.filter_level(LevelFilter::Info);
+    // RUST_LOG (ex. `RUST_LOG=debug`) can show the more detailed logs
+    if let Ok(filters) = env::var("RUST_LOG") {
+        logger.parse(&filters);
+    }
+
+    logger
+        .write_style(color.write_style(env::var_os("NO_COLOR").is_some()))
+        .format(|buf, record| {
+            let message = record.args().to_string();
+            let color = match record.level() {
+                Level::Error => Some(Color::Red),
+                Level::Warn => Some(Color::Yellow),
+                _ if message == "done!\n" => Some(Color::Green),
+                _ => None,
+            };
+
+            if let Some(color) = color {
+                let mut style = buf.style();
+                style.set_color(color);
+                write!(buf, "{}", style.value(message))
+            } else {
+                write!(buf, "{}", message)
+            }
+        })
+        .init();
+}
+
+fn main_ty(opt: Opt, manifest_path: Option<PathBuf>) -> Result<()> {
+    // Paths passed on the command line are relative to where wargo was run
+    let original_dir = env::current_dir()
+        .map_err(|err| format_err!("Could not find the current directory, error: {}", err))?;
+
+    if let Some(ref manifest_path) = manifest_path {
+        match opt {
+            Opt::Build { .. } | Opt::Update { .. } | Opt::ListReleases { .. } | Opt::Info { .. } => {
+                wargo::enter_manifest_dir(manifest_path)?
+            },
+            Opt::Init { .. } | Opt::New { .. } | Opt::Completions { .. } => {
+                return Err(format_err!("`--manifest-path` can only be used with the project commands \
+                                        (build, update, list-releases and info)!"));
+            },
+        }
+    } else {
+        // Like cargo, the project commands can be run from any subdirectory of the project
+        match opt {
+            Opt::Build { .. } | Opt::Update { .. } => wargo::enter_project_dir()?,
+            Opt::ListReleases { .. } | Opt::Info { .. } => { let _ = wargo::enter_project_dir(); },
+            _ => (),
+        }
+    }
+
+    let wargo_config = WargoConfig::load()?;
+
+    match opt {
+        Opt::Build {
+            js_path,
+            js_version,
+            progress,
+            timings,
+            keep_temp,
+            retries,
+            prefer_source,
+            strip_components,
+            use_git,
+            bindgen_target,
+            typescript,
+            js_repo,
+            github_host,
+            github_token,
+            package,
+            lib,
+            bin,
+            example,
+            release,
+            profile,
+            no_build,
+            deny_warnings,
+            index_name,
+            refresh_releases,
+            locked,
+            frozen,
+            post_build,
+            print_bindgen_cmd,
+            force_clean,
+            no_lock,
+        } => {
+            // Command-line flags take precedence over the wargo.toml
+            let file_config = wargo_config.build;
+            let defaults = build::BuildProjectConfig::default();
             build::build_project(build::BuildProjectConfig {
-                js_path,
+                js_path: js_path.map(|js_path| original_dir.join(js_path)).or(file_config.js_path),
+                js_version: js_version.or(file_config.js_version),
+                progress: progress.or(file_config.progress).unwrap_or(defaults.progress),
+                timings: timings || file_config.timings.unwrap_or(false),
+                keep_temp: keep_temp || file_config.keep_temp.unwrap_or(false),
+                retries: retries.or(file_config.retries).unwrap_or(defaults.retries),
+                prefer_source: prefer_source || file_config.prefer_source.unwrap_or(false),
+                strip_components: strip_components.or(file_config.strip_components),
+                use_git: use_git || file_config.use_git.unwrap_or(false),
+                bindgen_target: bindgen_target.or(file_config.bindgen_target).unwrap_or(defaults.bindgen_target),
+                typescript: typescript || file_config.typescript.unwrap_or(false),
+                js_repo: js_repo.or(file_config.js_repo).unwrap_or(defaults.js_repo),
+                github_host: github_host.or(file_config.github_host),
+                github_token,
+                package,
+                lib,
+                bin,
+                example,
+                profile: if release { Some("release".to_owned()) } else { profile.or(file_config.profile) },
+                no_build,
+                deny_warnings: deny_warnings || file_config.deny_warnings.unwrap_or(false),
+                index_name: index_name.or(file_config.index_name).unwrap_or(defaults.index_name),
+                refresh_releases,
+                locked,
+                frozen,
+                post_build: post_build.or(file_config.post_build),
+                print_bindgen_cmd,
+                force_clean,
+                no_lock,
             })
         },
-        Opt::Init { name } => {
-            init::initialize_entrypoint(name)
+        Opt::Init { name, dry_run, templates_from_release, wasm_rgame_version, release_repo } => {
+            // A dry run doesn't fetch the templates (or cache the releases)
+            if dry_run {
+                let current_dir = env::current_dir()
+                    .map_err(|err| format_err!("Could not find the current directory, error: {}", err))?;
+                return init::print_plan(name, &current_dir, &init::dry_run_templates(templates_from_release, wasm_rgame_version));
+            }
+
+            let templates = init_templates(templates_from_release, wasm_rgame_version, release_repo, wargo_config.build);
+            init::initialize_entrypoint(name, &templates)
         },
-        Opt::New { path, name } => {
+        Opt::New { path, name, dry_run, templates_from_release, wasm_rgame_version, release_repo } => {
+            if dry_run {
+                if path.exists() {
+                    warn!("{:?} already exists, creating the project would fail!\n", path);
+                }
+
+                info!("Would create the directory {:?}.\n", path);
+                return init::print_plan(name, &path, &init::dry_run_templates(templates_from_release, wasm_rgame_version));
+            }
+
+            let templates = init_templates(templates_from_release, wasm_rgame_version, release_repo, wargo_config.build);
             DirBuilder::new()
                 .create(path.clone())
                 .map_err(|err| format_err!("Could not create directory at path: {:?}, error: {}", path, err))?;
@@ -97,58 +518,91 @@ fn main_ty() -> Result<()> {
             env::set_current_dir(path.clone())
                 .map_err(|err| format_err!("Could not move into newly created path: {:?}, error: {}", path, err))?;
 
-            init::initialize_entrypoint(name)
+            init::initialize_entrypoint(name, &templates)
+        },
+        Opt::ListReleases { release_repo } => {
+            let in_project = Path::new("Cargo.toml").exists();
+            let repo = release_repo.into_release_repo(wargo_config.build, in_project);
+
+            // Outside of a project all of the releases are listed without a selection
+            let wasm_rgame_version = if in_project {
+                Some(wargo::wasm_rgame_version()?)
+            } else {
+                None
+            };
+
+            release::list_releases(wasm_rgame_version.as_ref(), &repo)
+        },
+        Opt::Update { release_repo, dry_run } => {
+            update::update_wasm_rgame(&release_repo.into_release_repo(wargo_config.build, true), dry_run)
+        },
+        Opt::Info { format } => {
+            info::print_info(format.unwrap_or(InfoFormat::Human))
+        },
+        Opt::Completions { shell } => {
+            Args::clap().gen_completions_to("wargo", shell, &mut io::stdout());
+            Ok(())
         },
     }
 }
 
-fn project_name() -> Result<String> {
-    let mut cargo_file = File::open("Cargo.toml")
-        .map_err(|err| format_err!("Cannot find Cargo.toml in project directory, error: {}", err))?;
+fn init_templates(
+    templates_from_release: bool,
+    wasm_rgame_version: Option<String>,
+    release_repo: ReleaseRepoOpt,
+    file_config: BuildConfig,
+) -> init::Templates {
+    // The project doesn't exist yet, the current directory may be another project
+    let repo = release_repo.into_release_repo(file_config, false);
+    let mut templates = if templates_from_release {
+        init::Templates::from_latest_release(&repo)
+    } else {
+        init::Templates::embedded()
+    };
 
-    let mut cargo_contents = String::new();
-    let _ = cargo_file.read_to_string(&mut cargo_contents)
-        .map_err(|err| format_err!("Cannot read Cargo.toml contents, error: {}", err))?;
+    templates.set_wasm_rgame_version(&init::wasm_rgame_version(wasm_rgame_version, &repo));
+    templates
+}
 
-    let cargo_toml = CargoToml::from_str(&cargo_contents)
-        .map_err(|err| format_err!("Cannot parse Cargo.toml, error: {}", err))?;
+/// When run as `cargo wargo ...`, cargo invokes the binary with `wargo` as the
+/// first argument, which is removed so the arguments parse the same as `wargo ...`.
+fn strip_cargo_subcommand(mut args: Vec<OsString>) -> Vec<OsString> {
+    if args.len() > 1 && args[1] == "wargo" {
+        args.remove(1);
+    }
 
-    Ok(cargo_toml.package.name.to_owned())
+    args
 }
 
-fn built_project_name(project_name: &str) -> String {
-    project_name.replace("-", "_")
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn wasm_rgame_version() -> Result<Version> {
-    let cargo_lock_contents = fs::read_to_string("Cargo.lock")
-        .map_err(|err| format_err!("Cannot find / read Cargo.lock in project directory, error: {}", err))?;
+    #[test]
+    fn strip_cargo_subcommand_only_strips_subcommand_name() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
 
-    if let Some(version) = cargo_lock::find_version("wasm-rgame", &cargo_lock_contents) {
-        Ok(version)
-    } else {
-        Err(format_err!("Cannot find wasm-rgame package in the Cargo.lock file!"))
+        assert_eq!(strip_cargo_subcommand(args(&["cargo-wargo", "wargo", "build"])), args(&["cargo-wargo", "build"]));
+        assert_eq!(strip_cargo_subcommand(args(&["wargo", "build"])), args(&["wargo", "build"]));
+        assert_eq!(strip_cargo_subcommand(args(&["wargo"])), args(&["wargo"]));
     }
-}
 
-/// Executes the command with process::Command, mapping both the error of
-/// executing the command and the status code + output to a Failure::Error
-fn execute_command(command: &str, args: &str, context: &str) -> Result<()> {
-    let output = Command::new(command)
-        .args(args.split_whitespace())
-        .output()
-        .map_err(|err| format_err!("Failed to execute, context: `{}`, error: {}\nFull command: `{} {}`", context, err, command, args))?;
-
-    if !output.status.success() {
-        return Err(format_err!(
-            "Command failed, context: `{}`\n\n\nStdout:\n{}\n\n\nStderr:\n{}\n\n\nFull command: `{} {}`",
-            context,
-            str::from_utf8(&output.stdout).unwrap(),
-            str::from_utf8(&output.stderr).unwrap(),
-            command,
-            args,
-        ));
+    #[test]
+    fn releases_are_only_cached_in_project() {
+        let release_repo_opt = || ReleaseRepoOpt::from_iter(&["release-repo"]);
+
+        assert!(release_repo_opt().into_release_repo(BuildConfig::default(), false).cache_path.is_none());
+        assert_eq!(
+            release_repo_opt().into_release_repo(BuildConfig::default(), true).cache_path,
+            Some(PathBuf::from(release::RELEASES_CACHE_PATH))
+        );
     }
 
-    Ok(())
+    #[test]
+    fn no_color_only_disables_auto_color() {
+        assert_eq!(ColorMode::Auto.write_style(true), WriteStyle::Never);
+        assert_eq!(ColorMode::Auto.write_style(false), WriteStyle::Auto);
+        assert_eq!(ColorMode::Always.write_style(true), WriteStyle::Always);
+        assert_eq!(ColorMode::Never.write_style(false), WriteStyle::Never);
+    }
 }
@@ -0,0 +1,63 @@
+//! Reading the resolved dependencies with `cargo metadata`, slower than parsing
+//! the Cargo.lock but handles what the parsing misses (ex. patched dependencies).
+use super::*;
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<MetadataPackage>,
+}
+
+#[derive(Deserialize)]
+struct MetadataPackage {
+    name: String,
+    version: String,
+}
+
+/// The version of the package in the dependency graph of the project, None if
+/// the project doesn't depend on the package.
+pub fn find_version(package_name: &str) -> Result<Option<Version>> {
+    let metadata_json = capture_command(
+        &tool::cargo(),
+        "metadata --format-version 1",
+        "Read the resolved dependencies of the project"
+    )?;
+
+    find_version_in(package_name, &metadata_json)
+}
+
+/// Same as `find_version`, but in the JSON output of `cargo metadata`. Like the
+/// Cargo.lock parsing, `-` and `_` in the package names are equivalent.
+fn find_version_in(package_name: &str, metadata_json: &str) -> Result<Option<Version>> {
+    let metadata: Metadata = serde_json::from_str(metadata_json)
+        .map_err(|err| format_err!("Could not parse the output of `cargo metadata`, error: {}", err))?;
+
+    let package_name = package_name.replace("_", "-");
+    let package = metadata.packages.into_iter()
+        .find(|package| package.name.replace("_", "-") == package_name);
+
+    match package {
+        Some(package) => Version::parse(&package.version)
+            .map(Some)
+            .map_err(|err| format_err!("Invalid version `{}` of {} in `cargo metadata`, error: {}", package.version, package.name, err)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METADATA_JSON : &'static str = include_str!("../data/cargo-metadata.example.json");
+
+    #[test]
+    fn find_version_in_metadata() {
+        assert_eq!(find_version_in("wasm-rgame", METADATA_JSON).unwrap(), Some(Version::new(0, 3, 1)));
+        assert_eq!(find_version_in("wasm_bindgen", METADATA_JSON).unwrap(), Some(Version::new(0, 2, 29)));
+        assert_eq!(find_version_in("wasm-rgame-js", METADATA_JSON).unwrap(), None);
+    }
+
+    #[test]
+    fn find_version_in_invalid_metadata() {
+        assert!(find_version_in("wasm-rgame", "warning: not json").is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use super::*;
+
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ProgressMode {
+    /// The spinner is drawn to stderr (same as the logs), so `Auto` only
+    /// enables it when stderr is a terminal.
+    fn enabled(&self) -> bool {
+        match *self {
+            ProgressMode::Auto => atty::is(atty::Stream::Stderr),
+            ProgressMode::Always => true,
+            ProgressMode::Never => false,
+        }
+    }
+}
+
+impl FromStr for ProgressMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<ProgressMode, String> {
+        match s {
+            "auto" => Ok(ProgressMode::Auto),
+            "always" => Ok(ProgressMode::Always),
+            "never" => Ok(ProgressMode::Never),
+            _ => Err(format!("Invalid progress mode: `{}`, expected one of: auto, always, never", s)),
+        }
+    }
+}
+
+/// Runs the long-running step, animating a spinner with the elapsed time
+/// while it runs if enabled. Otherwise falls back to plain "message.. done!" logging.
+pub fn run_step<T>(mode: ProgressMode, message: &str, step: impl FnOnce() -> Result<T>) -> Result<T> {
+    if !mode.enabled() {
+        info!("{}.. ", message);
+        let result = step()?;
+        info!("done!\n");
+        return Ok(result);
+    }
+
+    let start = Instant::now();
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(ProgressStyle::default_spinner().template("{spinner} {msg} [{elapsed}]"));
+    spinner.set_message(message);
+    spinner.enable_steady_tick(100);
+
+    let result = step();
+    let elapsed = start.elapsed();
+    spinner.finish_and_clear();
+
+    let result = result?;
+    info!("{}.. done! ({})\n", message, format_duration(elapsed));
+    Ok(result)
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    format!("{:.1}s", duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1_000_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_progress_modes() {
+        assert_eq!("auto".parse::<ProgressMode>(), Ok(ProgressMode::Auto));
+        assert_eq!("always".parse::<ProgressMode>(), Ok(ProgressMode::Always));
+        assert_eq!("never".parse::<ProgressMode>(), Ok(ProgressMode::Never));
+        assert!("sometimes".parse::<ProgressMode>().is_err());
+    }
+
+    #[test]
+    fn formats_duration_in_seconds() {
+        assert_eq!(format_duration(Duration::from_millis(48_230)), "48.2s");
+    }
+}
@@ -0,0 +1,92 @@
+//! A short-lived cache of the releases list, so repeated builds don't need to
+//! ask the GitHub API for the releases every time.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::*;
+
+/// How long the cached releases are used before they're fetched again.
+const RELEASES_CACHE_TTL_SECS : u64 = 10 * 60;
+
+#[derive(Serialize, Deserialize)]
+struct ReleasesCache {
+    /// The repository (and host) the releases were fetched from, the cache is
+    /// only used for the same source.
+    source: String,
+    fetched_at: u64,
+    releases: Vec<JsRelease>,
+}
+
+/// The current time in seconds since the unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// The cached releases if there are any for the source that aren't expired.
+pub fn load(cache_path: &Path, source: &str, now: u64) -> Option<Vec<JsRelease>> {
+    let contents = fs::read_to_string(cache_path).ok()?;
+    let cache = toml::from_str::<ReleasesCache>(&contents).ok()?;
+
+    let expired = now < cache.fetched_at || now - cache.fetched_at > RELEASES_CACHE_TTL_SECS;
+    if cache.source != source || expired {
+        return None;
+    }
+
+    Some(cache.releases)
+}
+
+pub fn store(cache_path: &Path, source: &str, now: u64, releases: &[JsRelease]) -> Result<()> {
+    let cache = ReleasesCache {
+        source: source.to_owned(),
+        fetched_at: now,
+        releases: releases.to_vec(),
+    };
+
+    let contents = toml::to_string(&cache)
+        .map_err(|err| format_err!("Could not serialize the releases cache, error: {}", err))?;
+    if let Some(cache_dir) = cache_path.parent() {
+        fs::create_dir_all(cache_dir)?;
+    }
+    fs::write(cache_path, contents)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn releases() -> Vec<JsRelease> {
+        vec![JsRelease {
+            tag_name: "v0.1.0".to_owned(),
+            tarball_url: "https://api.github.com/repos/DarrenTsung/wasm-rgame-js/tarball/v0.1.0".to_owned(),
+            assets: vec![JsReleaseAsset {
+                name: "wasm-rgame-js.tar.gz".to_owned(),
+                browser_download_url: "https://github.com/DarrenTsung/wasm-rgame-js/releases/download/v0.1.0/wasm-rgame-js.tar.gz".to_owned(),
+                url: "https://api.github.com/repos/DarrenTsung/wasm-rgame-js/releases/assets/1".to_owned(),
+            }],
+        }]
+    }
+
+    #[test]
+    fn load_returns_fresh_releases_for_same_source() {
+        let tmp_dir = TempDir::new().unwrap();
+        let cache_path = tmp_dir.path().join("cache").join("releases.toml");
+        store(&cache_path, "DarrenTsung/wasm-rgame-js", 1_000, &releases()).unwrap();
+
+        let cached = load(&cache_path, "DarrenTsung/wasm-rgame-js", 1_060).expect("cache is fresh");
+        assert_eq!(cached, releases());
+    }
+
+    #[test]
+    fn load_ignores_other_source_and_expired_cache() {
+        let tmp_dir = TempDir::new().unwrap();
+        let cache_path = tmp_dir.path().join("releases.toml");
+        store(&cache_path, "DarrenTsung/wasm-rgame-js", 1_000, &releases()).unwrap();
+
+        assert!(load(&cache_path, "someone/wasm-rgame-js-fork", 1_060).is_none());
+        assert!(load(&cache_path, "DarrenTsung/wasm-rgame-js", 1_000 + RELEASES_CACHE_TTL_SECS + 1).is_none());
+        assert!(load(&tmp_dir.path().join("missing.toml"), "DarrenTsung/wasm-rgame-js", 1_060).is_none());
+    }
+}
@@ -1,7 +1,7 @@
 use semver::Version;
 
 /// Pick the correct version given some main version. If no version is found
-/// then the latest version is assumed to work.
+/// (or there are no items) then None is returned.
 ///
 /// For example: if the main version is 0.3.1, and the versions are
 /// [0.2.0, 0.3.0] then 0.3.0 will be chosen because it was the most up-to-date.
@@ -14,8 +14,6 @@ pub fn choose_version_by_key<T>(
     key_fn: impl Fn(&T) -> Option<Version>,
 ) -> Option<T>
 {
-    assert!(!items.is_empty());
-
     let mut filtered_items = items.into_iter()
         .map(|i| {
             let version = key_fn(&i);
@@ -63,6 +61,14 @@ mod tests {
         assert_eq!(chosen, Some("0.3.1"));
     }
 
+    #[test]
+    fn choose_version_by_key_with_no_items() {
+        let main_version = Version::parse("0.3.1").unwrap();
+
+        let chosen = choose_version_by_key(main_version, Vec::<&str>::new(), |s| Version::parse(s).ok());
+        assert_eq!(chosen, None);
+    }
+
     #[test]
     fn choose_version_by_key_with_no_matching() {
         let main_version = Version::parse("0.1.1").unwrap();
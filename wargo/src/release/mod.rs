@@ -0,0 +1,250 @@
+//! Fetching the wasm-rgame-js releases from GitHub and choosing the one that
+//! matches the project's wasm-rgame version.
+use super::*;
+
+use hubcaps::releases::Release;
+
+use retry::with_retries;
+
+mod cache;
+
+mod choose_version;
+use self::choose_version::choose_version_by_key;
+
+pub const DEFAULT_JS_REPO : &'static str = "DarrenTsung/wasm-rgame-js";
+
+/// Where the releases list is cached in the project, see `cache`.
+pub const RELEASES_CACHE_PATH : &'static str = "target/wasm-rgame/.cache/releases.toml";
+
+/// The parts of a GitHub release that wargo uses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsRelease {
+    pub tag_name: String,
+    pub tarball_url: String,
+    #[serde(default)]
+    pub assets: Vec<JsReleaseAsset>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+    /// The API url of the asset (`/repos/{owner}/{repo}/releases/assets/{id}`), which
+    /// assets of private repositories are downloaded from. Empty for cached releases
+    /// from before it was stored.
+    #[serde(default)]
+    pub url: String,
+}
+
+impl From<Release> for JsRelease {
+    fn from(release: Release) -> JsRelease {
+        JsRelease {
+            tag_name: release.tag_name,
+            tarball_url: release.tarball_url,
+            assets: release.assets.into_iter()
+                .map(|asset| JsReleaseAsset { name: asset.name, browser_download_url: asset.browser_download_url, url: asset.url })
+                .collect(),
+        }
+    }
+}
+
+/// The GitHub repository to fetch the releases from and how to access it.
+pub struct ReleaseRepo {
+    /// The repository in the form "owner/repo", ex. "DarrenTsung/wasm-rgame-js".
+    pub repo: String,
+    /// The API url of a GitHub Enterprise host, defaults to github.com.
+    pub github_host: Option<String>,
+    pub credentials: Option<Credentials>,
+    pub retries: u32,
+    /// Where to cache the releases list, no caching is done when None.
+    pub cache_path: Option<PathBuf>,
+    /// Fetch the releases even if there is a fresh cached releases list.
+    pub refresh: bool,
+}
+
+impl ReleaseRepo {
+    /// The token to also send when downloading the release files.
+    pub fn github_token(&self) -> Option<&str> {
+        match self.credentials {
+            Some(Credentials::Token(ref token)) => Some(token),
+            _ => None,
+        }
+    }
+
+    /// Identifies the releases in the cache, different repositories (or hosts)
+    /// have different releases.
+    fn cache_source(&self) -> String {
+        match self.github_host {
+            Some(ref github_host) => format!("{}/{}", github_host, self.repo),
+            None => self.repo.clone(),
+        }
+    }
+}
+
+/// Fetches all of the releases of the repository, using the cached releases
+/// list when it's fresh.
+pub fn fetch_releases(repo: &ReleaseRepo) -> Result<Vec<JsRelease>> {
+    if let (Some(cache_path), false) = (repo.cache_path.as_ref(), repo.refresh) {
+        if let Some(releases) = cache::load(cache_path, &repo.cache_source(), cache::now()) {
+            debug!("Using the cached releases of {} from {:?}.\n", repo.repo, cache_path);
+            return Ok(releases);
+        }
+    }
+
+    let releases = fetch_releases_uncached(repo)?;
+    if let Some(ref cache_path) = repo.cache_path {
+        if let Err(err) = cache::store(cache_path, &repo.cache_source(), cache::now(), &releases) {
+            debug!("Could not cache the releases in {:?}, error: {}\n", cache_path, err);
+        }
+    }
+
+    Ok(releases)
+}
+
+fn fetch_releases_uncached(repo: &ReleaseRepo) -> Result<Vec<JsRelease>> {
+    let (repo_owner, repo_name) = split_repo(&repo.repo)?;
+
+    let mut core = Core::new().unwrap();
+    let github = if let Some(ref github_host) = repo.github_host {
+        Github::host(github_host.clone(), "wargo-agent".to_string(), repo.credentials.clone(), &core.handle())
+    } else {
+        Github::new("wargo-agent".to_string(), repo.credentials.clone(), &core.handle())
+    };
+
+    let repo_releases = github.repo(repo_owner, repo_name).releases();
+    let releases = with_retries(repo.retries, "fetch the wasm-rgame-js releases", || {
+        core.run(repo_releases.list()).map_err(retry::github_attempt_error)
+    })?;
+    if releases.is_empty() {
+        return Err(WargoError::NoReleases { repo: repo.repo.clone() }.into());
+    }
+
+    Ok(releases.into_iter().map(JsRelease::from).collect())
+}
+
+/// Fetches the release with the `js_version` tag if provided, otherwise the
+/// latest release that is compatible with the wasm-rgame version.
+pub fn fetch_matching_release(wasm_rgame_version: &Version, js_version: Option<&str>, repo: &ReleaseRepo) -> Result<JsRelease> {
+    let releases = fetch_releases(repo)?;
+    select_release(wasm_rgame_version, js_version, releases)
+}
+
+/// Picks the release that was explicitly requested, otherwise the release
+/// matching the wasm-rgame version, out of the releases.
+pub fn select_release(wasm_rgame_version: &Version, js_version: Option<&str>, releases: Vec<JsRelease>) -> Result<JsRelease> {
+    if let Some(js_version) = js_version {
+        // Accept both the tag ("v0.1.0") and the bare version ("0.1.0")
+        let prefixed_js_version = format!("v{}", js_version);
+        let chosen_release = releases.into_iter()
+            .find(|r| r.tag_name == js_version || r.tag_name == prefixed_js_version);
+
+        return chosen_release.ok_or_else(|| WargoError::ReleaseTagNotFound { tag: js_version.to_owned() }.into());
+    }
+
+    let chosen_release = choose_matching_release(wasm_rgame_version, releases);
+    if chosen_release.is_none() {
+        return Err(WargoError::NoCompatibleRelease { wasm_rgame_version: wasm_rgame_version.clone() }.into());
+    }
+
+    let chosen_release = chosen_release.unwrap();
+    if let Some(chosen_version) = release_version(&chosen_release) {
+        if &chosen_version < wasm_rgame_version {
+            wargo_warn!("No release of wasm-rgame-js matches wasm-rgame version `{}`, building against \
+                   the older wasm-rgame-js version `{}` which may not be compatible!\n", wasm_rgame_version, chosen_version);
+        }
+    }
+
+    Ok(chosen_release)
+}
+
+/// Prints the tag and version of each release, marking the release that would
+/// be chosen for the wasm-rgame version (when building inside a project).
+pub fn list_releases(wasm_rgame_version: Option<&Version>, repo: &ReleaseRepo) -> Result<()> {
+    let releases = fetch_releases(repo)?;
+
+    let chosen_tag = wasm_rgame_version.and_then(|wasm_rgame_version| {
+        choose_version_by_key(wasm_rgame_version.clone(), releases.iter().collect(), |r| release_version(r))
+            .map(|r| r.tag_name.clone())
+    });
+
+    info!("Releases of {}:\n", repo.repo);
+    for release in &releases {
+        let version = release_version(release)
+            .map(|version| version.to_string())
+            .unwrap_or_else(|| "invalid version".to_owned());
+        let marker = if chosen_tag.as_ref() == Some(&release.tag_name) { " (selected)" } else { "" };
+        info!("  {} ({}){}\n", release.tag_name, version, marker);
+    }
+
+    match wasm_rgame_version {
+        Some(wasm_rgame_version) if chosen_tag.is_none() => {
+            info!("No release is compatible with wasm-rgame version `{}`.\n", wasm_rgame_version);
+        },
+        Some(wasm_rgame_version) => {
+            info!("The selected release is chosen for wasm-rgame version `{}`.\n", wasm_rgame_version);
+        },
+        None => (),
+    }
+
+    Ok(())
+}
+
+/// Fetches the release with the greatest version, for when there is no project
+/// wasm-rgame version to match yet (ex. `wargo init`).
+pub fn fetch_latest_release(repo: &ReleaseRepo) -> Result<JsRelease> {
+    let releases = fetch_releases(repo)?;
+
+    releases.into_iter()
+        .filter_map(|release| release_version(&release).map(|version| (version, release)))
+        .max_by(|(version, _), (other_version, _)| version.cmp(other_version))
+        .map(|(_, release)| release)
+        .ok_or_else(|| WargoError::NoReleases { repo: repo.repo.clone() }.into())
+}
+
+/// The latest release that is compatible with the wasm-rgame version.
+pub fn choose_matching_release(wasm_rgame_version: &Version, releases: Vec<JsRelease>) -> Option<JsRelease> {
+    choose_version_by_key(wasm_rgame_version.clone(), releases, release_version)
+}
+
+/// The version of the release from its tag, or None if the tag isn't a version
+/// (ex. "nightly") so the release is skipped.
+pub fn release_version(release: &JsRelease) -> Option<Version> {
+    tag_version(&release.tag_name)
+}
+
+/// Tags look like: "v0.1.0" (or "0.1.0"), need to become "0.1.0"
+fn tag_version(tag_name: &str) -> Option<Version> {
+    let version_str = tag_name.strip_prefix('v').unwrap_or(tag_name);
+    Version::parse(version_str).ok()
+}
+
+/// Splits the "owner/repo" into its owner and repo name.
+fn split_repo(repo: &str) -> Result<(&str, &str)> {
+    let mut parts = repo.split('/');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(owner), Some(repo), None) if !owner.is_empty() && !repo.is_empty() => Ok((owner, repo)),
+        _ => Err(format_err!("Invalid js repo: `{}`, expected the form `owner/repo`!", repo)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_repo_into_owner_and_name() {
+        assert_eq!(split_repo("DarrenTsung/wasm-rgame-js").unwrap(), ("DarrenTsung", "wasm-rgame-js"));
+        assert!(split_repo("wasm-rgame-js").is_err());
+        assert!(split_repo("DarrenTsung/").is_err());
+        assert!(split_repo("a/b/c").is_err());
+    }
+
+    #[test]
+    fn tag_version_parses_optional_v_prefix() {
+        assert_eq!(tag_version("v0.1.0"), Some(Version::parse("0.1.0").unwrap()));
+        assert_eq!(tag_version("0.1.0"), Some(Version::parse("0.1.0").unwrap()));
+        assert_eq!(tag_version("nightly"), None);
+        assert_eq!(tag_version("v"), None);
+        assert_eq!(tag_version(""), None);
+    }
+}
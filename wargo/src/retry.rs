@@ -0,0 +1,118 @@
+use super::*;
+
+use std::thread;
+use std::time::Duration;
+
+const INITIAL_BACKOFF_MS : u64 = 500;
+const MAX_BACKOFF_MS : u64 = 8_000;
+/// The delay is capped long before this many doublings, clamping the exponent
+/// keeps the shift from overflowing for large attempt numbers.
+const MAX_BACKOFF_EXPONENT : u32 = 16;
+
+/// The error of a single attempt, only transient errors are retried.
+pub enum AttemptError {
+    Transient(failure::Error),
+    Permanent(failure::Error),
+}
+
+/// Runs the operation, retrying up to `retries` times with exponential
+/// backoff as long as the operation fails with a transient error.
+pub fn with_retries<T>(retries: u32, description: &str, mut operation: impl FnMut() -> std::result::Result<T, AttemptError>) -> Result<T> {
+    let mut attempt = 0;
+    loop {
+        match operation() {
+            Ok(value) => return Ok(value),
+            Err(AttemptError::Permanent(err)) => return Err(err),
+            Err(AttemptError::Transient(err)) => {
+                if attempt >= retries {
+                    return Err(err);
+                }
+
+                let backoff = backoff_delay(attempt);
+                debug!("Failed to {} (attempt {} of {}), retrying in {}, error: {}\n", description, attempt + 1, retries + 1, progress::format_duration(backoff), err);
+                thread::sleep(backoff);
+                attempt += 1;
+            },
+        }
+    }
+}
+
+/// Doubles the delay for each attempt, capped at MAX_BACKOFF_MS.
+fn backoff_delay(attempt: u32) -> Duration {
+    let delay_ms = INITIAL_BACKOFF_MS.saturating_mul(1 << attempt.min(MAX_BACKOFF_EXPONENT));
+    Duration::from_millis(delay_ms.min(MAX_BACKOFF_MS))
+}
+
+/// Server errors and connection-level failures are transient, anything else
+/// (not found, unauthorized, bad response) will not be fixed by retrying.
+pub fn github_attempt_error(err: hubcaps::Error) -> AttemptError {
+    let (is_transient, status) = match *err.kind() {
+        hubcaps::ErrorKind::Fault { code, .. } => (code.is_server_error(), Some(code.as_u16())),
+        hubcaps::ErrorKind::Http(_) | hubcaps::ErrorKind::IO(_) => (true, None),
+        _ => (false, None),
+    };
+
+    let err = WargoError::NetworkError { message: format!("Failed to fetch the releases from GitHub, error: {}", err), status }.into();
+    if is_transient {
+        AttemptError::Transient(err)
+    } else {
+        AttemptError::Permanent(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_delay_doubles_and_caps() {
+        assert_eq!(backoff_delay(0), Duration::from_millis(500));
+        assert_eq!(backoff_delay(1), Duration::from_millis(1_000));
+        assert_eq!(backoff_delay(3), Duration::from_millis(4_000));
+        assert_eq!(backoff_delay(4), Duration::from_millis(8_000));
+        assert_eq!(backoff_delay(10), Duration::from_millis(8_000));
+        assert_eq!(backoff_delay(100), Duration::from_millis(8_000));
+        for attempt in &[60, 63, 64, u32::MAX] {
+            assert_eq!(backoff_delay(*attempt), Duration::from_millis(8_000));
+        }
+    }
+
+    #[test]
+    fn with_retries_does_not_retry_permanent_errors() {
+        let mut attempts = 0;
+        let result: Result<()> = with_retries(3, "test", || {
+            attempts += 1;
+            Err(AttemptError::Permanent(format_err!("not found")))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn with_retries_retries_transient_errors() {
+        let mut attempts = 0;
+        let result = with_retries(3, "test", || {
+            attempts += 1;
+            if attempts < 2 {
+                Err(AttemptError::Transient(format_err!("connection reset")))
+            } else {
+                Ok(attempts)
+            }
+        });
+
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[test]
+    fn with_retries_gives_up_after_retries() {
+        let mut attempts = 0;
+        let result: Result<()> = with_retries(0, "test", || {
+            attempts += 1;
+            Err(AttemptError::Transient(format_err!("timed out")))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+}
@@ -0,0 +1,42 @@
+//! The executables run by wargo. Each can be overridden with an environment
+//! variable (ex. `CARGO=/opt/rust/bin/cargo`) to pin the exact binary, as cargo
+//! subcommands conventionally do.
+use std::env;
+use std::ffi::OsString;
+
+pub fn cargo() -> String {
+    resolve(env::var_os("CARGO"), "cargo")
+}
+
+pub fn rustup() -> String {
+    resolve(env::var_os("RUSTUP"), "rustup")
+}
+
+pub fn wasm_bindgen() -> String {
+    resolve(env::var_os("WASM_BINDGEN"), "wasm-bindgen")
+}
+
+pub fn git() -> String {
+    resolve(env::var_os("GIT"), "git")
+}
+
+/// The override if it's set (and not empty), otherwise the bare command name
+/// to be found on the PATH.
+fn resolve(override_value: Option<OsString>, command: &str) -> String {
+    match override_value {
+        Some(ref value) if !value.is_empty() => value.to_string_lossy().into_owned(),
+        _ => command.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_prefers_override() {
+        assert_eq!(resolve(Some(OsString::from("/opt/rust/bin/cargo")), "cargo"), "/opt/rust/bin/cargo");
+        assert_eq!(resolve(Some(OsString::new()), "cargo"), "cargo");
+        assert_eq!(resolve(None, "cargo"), "cargo");
+    }
+}
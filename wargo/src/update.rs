@@ -0,0 +1,77 @@
+use super::*;
+
+use semver::VersionReq;
+
+/// Finds the latest wasm-rgame-js release within the Cargo.toml requirement of
+/// wasm-rgame and updates the locked wasm-rgame version with `cargo update`.
+pub fn update_wasm_rgame(repo: &ReleaseRepo, dry_run: bool) -> Result<()> {
+    let version_req = cargo_toml()?.dependency_req("wasm-rgame")
+        .ok_or_else(|| format_err!("Could not find a version requirement for `wasm-rgame` in the Cargo.toml \
+                                    [dependencies], git and path dependencies can't be updated!"))?;
+
+    ensure_cargo_lock()?;
+    let locked_version = wasm_rgame_version()?;
+
+    let releases = release::fetch_releases(repo)?;
+    let release_versions = releases.iter().filter_map(release::release_version).collect::<Vec<_>>();
+    let latest_version = match latest_matching_version(&version_req, &release_versions) {
+        Some(latest_version) => latest_version,
+        None => {
+            info!("No release of {} matches the wasm-rgame requirement `{}`.\n", repo.repo, version_req);
+            return Ok(());
+        },
+    };
+
+    if latest_version <= locked_version {
+        info!("wasm-rgame `{}` is up to date (requirement: `{}`).\n", locked_version, version_req);
+        return Ok(());
+    }
+
+    if dry_run {
+        info!("Would update wasm-rgame from `{}` to `{}` (requirement: `{}`).\n", locked_version, latest_version, version_req);
+        return Ok(());
+    }
+
+    info!("Updating wasm-rgame from `{}`.. ", locked_version);
+    execute_command(
+        &tool::cargo(),
+        "update -p wasm-rgame",
+        "Update the wasm-rgame version in the Cargo.lock"
+    )?;
+    info!("done!\n");
+
+    let updated_version = wasm_rgame_version()?;
+    if updated_version < latest_version {
+        wargo_warn!("wasm-rgame was updated to `{}`, but the latest wasm-rgame-js release is `{}`.\n", updated_version, latest_version);
+    } else {
+        info!("Updated wasm-rgame to `{}`.\n", updated_version);
+    }
+
+    Ok(())
+}
+
+/// The greatest version that satisfies the requirement.
+fn latest_matching_version(version_req: &VersionReq, versions: &[Version]) -> Option<Version> {
+    versions.iter()
+        .filter(|version| version_req.matches(version))
+        .max()
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latest_matching_version_respects_requirement() {
+        let versions = ["0.2.0", "0.3.0", "0.3.2", "0.4.0"].iter()
+            .map(|v| Version::parse(v).unwrap())
+            .collect::<Vec<_>>();
+
+        let version_req = VersionReq::parse("0.3").unwrap();
+        assert_eq!(latest_matching_version(&version_req, &versions), Some(Version::parse("0.3.2").unwrap()));
+
+        let version_req = VersionReq::parse("0.5").unwrap();
+        assert_eq!(latest_matching_version(&version_req, &versions), None);
+    }
+}